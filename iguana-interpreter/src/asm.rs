@@ -0,0 +1,122 @@
+//! Script assembly in the BCH notation: opcodes by name (e.g. `OP_CHECKSIG`), data pushes
+//! as `<0x..>` and number pushes as `<123>`.
+
+use bitcoin_cash::{deserialize_ops, Op, TaggedOp};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+
+use crate::{encode_script_num, introspection_opcode_name};
+
+lazy_static! {
+    /// Names of all single byte opcodes, i.e. everything except the data pushes 0x01 to 0x4e.
+    static ref OPCODE_NAMES: HashMap<String, u8> = (0x00..=0xffu8)
+        .filter(|code| !(0x01..=0x4e).contains(code))
+        .filter_map(|code| {
+            let ops = deserialize_ops(&[code]).ok()?;
+            match ops.as_slice() {
+                [op] => Some((op_to_asm(op), code)),
+                _ => None,
+            }
+        })
+        .collect();
+}
+
+#[derive(Error, Clone, Debug)]
+pub enum AsmError {
+    #[error("Unknown opcode: {0}")]
+    UnknownOpcode(String),
+
+    #[error("Invalid push: {0}")]
+    InvalidPush(String),
+
+    #[error("Invalid script: {0}")]
+    InvalidScript(String),
+}
+
+/// Assembly of a single op. Pushes of the numbers -1 to 16 use their opcodes.
+pub fn op_to_asm(op: &Op) -> String {
+    match op {
+        Op::Code(opcode) => format!("{:?}", opcode),
+        Op::Invalid(code) => match introspection_opcode_name(*code) {
+            Some(name) => name.to_string(),
+            None => format!("OP_UNKNOWN_{:02x}", code),
+        },
+        Op::PushBoolean(boolean) => if *boolean { "OP_1" } else { "OP_0" }.to_string(),
+        Op::PushInteger(int) => match int.value() {
+            -1 => "OP_1NEGATE".to_string(),
+            0 => "OP_0".to_string(),
+            value @ 1..=16 => format!("OP_{}", value),
+            value => format!("<{}>", value),
+        },
+        Op::PushByteArray { array, .. } if array.is_empty() => "OP_0".to_string(),
+        Op::PushByteArray { array, .. } => format!("<0x{}>", hex::encode(&array[..])),
+    }
+}
+
+/// Assembly of a script, with ops separated by spaces.
+pub fn script_to_asm(ops: &[TaggedOp]) -> String {
+    ops.iter()
+        .map(|op| op_to_asm(&op.op))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses assembly as written by `script_to_asm`. Pushes are encoded minimally, and
+/// OP_FALSE/OP_TRUE are accepted as aliases of OP_0/OP_1.
+pub fn script_from_asm(asm: &str) -> Result<Vec<Op>, AsmError> {
+    let mut script = Vec::new();
+    for token in asm.split_whitespace() {
+        if let Some(push) = token.strip_prefix('<').and_then(|token| token.strip_suffix('>')) {
+            let data = if let Some(hex) = push.strip_prefix("0x") {
+                hex::decode(hex).map_err(|_| AsmError::InvalidPush(token.to_string()))?
+            } else {
+                let number = push
+                    .parse::<i64>()
+                    .map_err(|_| AsmError::InvalidPush(token.to_string()))?;
+                encode_script_num(number)
+            };
+            push_data(&mut script, &data);
+        } else {
+            let code = match token {
+                "OP_FALSE" => 0x00,
+                "OP_TRUE" => 0x51,
+                _ => *OPCODE_NAMES
+                    .get(token)
+                    .ok_or_else(|| AsmError::UnknownOpcode(token.to_string()))?,
+            };
+            script.push(code);
+        }
+    }
+    let ops = deserialize_ops(&script).map_err(|err| AsmError::InvalidScript(err.to_string()))?;
+    // all pushes have been encoded minimally above
+    Ok(ops
+        .into_iter()
+        .map(|op| match op {
+            Op::PushByteArray { array, .. } => Op::PushByteArray { array, is_minimal: true },
+            op => op,
+        })
+        .collect())
+}
+
+/// Appends the minimal push of `data`.
+fn push_data(script: &mut Vec<u8>, data: &[u8]) {
+    match data {
+        [] => script.push(0x00),
+        [value @ 1..=16] => script.push(0x50 + value),
+        [0x81] => script.push(0x4f),
+        _ => {
+            if data.len() <= 0x4b {
+                script.push(data.len() as u8);
+            } else if data.len() <= 0xff {
+                script.extend_from_slice(&[0x4c, data.len() as u8]);
+            } else if data.len() <= 0xffff {
+                script.push(0x4d);
+                script.extend_from_slice(&(data.len() as u16).to_le_bytes());
+            } else {
+                script.push(0x4e);
+                script.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            }
+            script.extend_from_slice(data);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Breakpoints, which stop `ScriptInterpreter::run_until_break`.
+
+use bitcoin_cash::{Op, StackItemDelta, ECC};
+
+use crate::{introspection_opcode_name, ScriptError, ScriptInterpreter, ScriptPhase};
+
+/// Identifies a breakpoint added with `ScriptInterpreter::add_breakpoint`.
+pub type BreakpointId = usize;
+
+/// Condition on which `run_until_break` stops. Breakpoints on ops stop before the op is
+/// run, conditions on the stack are checked after each op.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Breakpoint {
+    /// Stop at the op with the given index in the script of the given phase.
+    Op {
+        phase: ScriptPhase,
+        instruction_pointer: usize,
+    },
+    /// Stop at ops compiled from the given line of the given source file.
+    Source { src_file: String, src_line: u32 },
+    /// Stop at every op with the given name, e.g. "OP_CHECKSIG".
+    Opcode(String),
+    /// Stop once the stack has more than the given number of items.
+    StackDepthAbove(usize),
+    /// Stop once an item with the given name has been pushed.
+    ItemPushed(String),
+}
+
+/// Why `run_until_break` or `run_to_end` returned.
+#[derive(Clone, Debug)]
+pub enum StopReason {
+    /// The breakpoint with the given id has been hit.
+    Breakpoint(BreakpointId),
+    /// All ops have been run.
+    Finished,
+    /// An op failed.
+    Error(ScriptError),
+}
+
+impl<E: ECC> ScriptInterpreter<E> {
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> BreakpointId {
+        let id = self.next_breakpoint_id;
+        self.next_breakpoint_id += 1;
+        self.breakpoints.push((id, breakpoint));
+        id
+    }
+
+    /// Removes the breakpoint with the given id. Returns false if there is no such breakpoint.
+    pub fn remove_breakpoint(&mut self, id: BreakpointId) -> bool {
+        let num_breakpoints = self.breakpoints.len();
+        self.breakpoints.retain(|&(breakpoint_id, _)| breakpoint_id != id);
+        self.breakpoints.len() != num_breakpoints
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn breakpoints(&self) -> &[(BreakpointId, Breakpoint)] {
+        &self.breakpoints
+    }
+
+    /// Runs ops until a breakpoint is hit, the script finishes or an op fails. Always runs at
+    /// least one op, so calling it again continues after the breakpoint that has been hit.
+    pub fn run_until_break(&mut self) -> StopReason {
+        let mut is_first = true;
+        while !self.is_finished() {
+            if !is_first {
+                if let Some(id) = self.hit_op_breakpoint() {
+                    return StopReason::Breakpoint(id);
+                }
+            }
+            is_first = false;
+            if let Err(err) = self.run_next_op() {
+                return StopReason::Error(err);
+            }
+            if let Some(id) = self.hit_stack_breakpoint() {
+                return StopReason::Breakpoint(id);
+            }
+        }
+        StopReason::Finished
+    }
+
+    /// Runs all remaining ops, ignoring breakpoints.
+    pub fn run_to_end(&mut self) -> StopReason {
+        while !self.is_finished() {
+            if let Err(err) = self.run_next_op() {
+                return StopReason::Error(err);
+            }
+        }
+        StopReason::Finished
+    }
+
+    fn hit_op_breakpoint(&self) -> Option<BreakpointId> {
+        let op = self.script.ops_arc().get(self.instruction_pointer)?;
+        self.breakpoints
+            .iter()
+            .find(|(_, breakpoint)| match breakpoint {
+                Breakpoint::Op {
+                    phase,
+                    instruction_pointer,
+                } => *phase == self.phase && *instruction_pointer == self.instruction_pointer,
+                Breakpoint::Source { src_file, src_line } => {
+                    op.src_file == src_file.as_str() && op.src_line == *src_line
+                }
+                Breakpoint::Opcode(name) => match op.op {
+                    Op::Code(opcode) => format!("{:?}", opcode) == *name,
+                    Op::Invalid(code) => introspection_opcode_name(code) == Some(name.as_str()),
+                    _ => false,
+                },
+                _ => false,
+            })
+            .map(|&(id, _)| id)
+    }
+
+    fn hit_stack_breakpoint(&self) -> Option<BreakpointId> {
+        self.breakpoints
+            .iter()
+            .find(|(_, breakpoint)| match breakpoint {
+                Breakpoint::StackDepthAbove(depth) => self.stack.len() > *depth,
+                Breakpoint::ItemPushed(name) => self.stack.iter().any(|item| {
+                    item.delta == StackItemDelta::Added
+                        && item.name.as_ref().map(|item_name| item_name.as_ref() == name) == Some(true)
+                }),
+                _ => false,
+            })
+            .map(|&(id, _)| id)
+    }
+}
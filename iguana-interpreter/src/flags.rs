@@ -0,0 +1,89 @@
+bitflags! {
+    /// Script verification flags, following the flags of the reference implementation.
+    pub struct VerifyFlags: u32 {
+        const NONE = 0;
+
+        /// Evaluate P2SH redeem scripts.
+        const P2SH = 1 << 0;
+
+        /// Require public keys to be compressed or uncompressed and sighash types to be defined.
+        const STRICTENC = 1 << 1;
+
+        /// Require ECDSA signatures to be strictly DER encoded (BIP66).
+        const DERSIG = 1 << 2;
+
+        /// Require the S value of ECDSA signatures to be at most half the curve order.
+        const LOW_S = 1 << 3;
+
+        /// Require the dummy element of legacy OP_CHECKMULTISIG to be empty.
+        const NULLDUMMY = 1 << 4;
+
+        /// Require the input script to only contain push ops.
+        const SIGPUSHONLY = 1 << 5;
+
+        /// Require pushes and script numbers to be minimally encoded.
+        const MINIMALDATA = 1 << 6;
+
+        /// Fail on the reserved OP_NOPx opcodes.
+        const DISCOURAGE_UPGRADABLE_NOPS = 1 << 7;
+
+        /// Require exactly one item to be left on the stack after evaluation.
+        const CLEANSTACK = 1 << 8;
+
+        /// Evaluate OP_CHECKLOCKTIMEVERIFY (BIP65), otherwise it is a NOP.
+        const CHECKLOCKTIMEVERIFY = 1 << 9;
+
+        /// Evaluate OP_CHECKSEQUENCEVERIFY (BIP112), otherwise it is a NOP.
+        const CHECKSEQUENCEVERIFY = 1 << 10;
+
+        /// Require the argument of OP_IF and OP_NOTIF to be either empty or exactly 0x01.
+        const MINIMALIF = 1 << 13;
+
+        /// Require failed signature checks to have empty signatures.
+        const NULLFAIL = 1 << 14;
+
+        /// Require signatures to use SIGHASH_FORKID.
+        const SIGHASH_FORKID = 1 << 16;
+
+        /// Interpret a non-empty dummy element of OP_CHECKMULTISIG as Schnorr bitfield.
+        const SCHNORR_MULTISIG = 1 << 21;
+
+        /// Use 8 byte script numbers for arithmetic, with overflow checks, and enable OP_MUL.
+        const INTEGERS_64BIT = 1 << 24;
+
+        /// Enable the native introspection opcodes (OP_INPUTINDEX to OP_OUTPUTBYTECODE).
+        const NATIVE_INTROSPECTION = 1 << 25;
+
+        /// Enable CashTokens, i.e. the token introspection opcodes and token prefixes.
+        const TOKENS = 1 << 27;
+
+        /// Enforce the stack, element, script, op count and SigChecks limits. Without this flag,
+        /// the usage is still tracked, which allows measuring how close a script is to each limit.
+        /// Not a flag of the reference implementation, where the limits are always enforced.
+        const ENFORCE_LIMITS = 1 << 31;
+
+        /// Flags enforced by the network's consensus rules.
+        const CONSENSUS = Self::P2SH.bits
+            | Self::STRICTENC.bits
+            | Self::DERSIG.bits
+            | Self::LOW_S.bits
+            | Self::SIGPUSHONLY.bits
+            | Self::MINIMALDATA.bits
+            | Self::CLEANSTACK.bits
+            | Self::CHECKLOCKTIMEVERIFY.bits
+            | Self::CHECKSEQUENCEVERIFY.bits
+            | Self::NULLFAIL.bits
+            | Self::SIGHASH_FORKID.bits
+            | Self::SCHNORR_MULTISIG.bits
+            | Self::INTEGERS_64BIT.bits
+            | Self::NATIVE_INTROSPECTION.bits
+            | Self::TOKENS.bits
+            | Self::ENFORCE_LIMITS.bits;
+
+        /// Flags enforced by nodes when accepting transactions into their mempool.
+        const STANDARD = Self::CONSENSUS.bits
+            | Self::NULLDUMMY.bits
+            | Self::DISCOURAGE_UPGRADABLE_NOPS.bits
+            | Self::MINIMALIF.bits;
+    }
+}
@@ -0,0 +1,118 @@
+//! Snapshots of the interpreter state before each op, which allow stepping back.
+
+use bitcoin_cash::{Script, ECC};
+use std::sync::Arc;
+
+use crate::{ResourceUsage, ScriptError, ScriptInterpreter, ScriptPhase, StackItem};
+
+/// State of the interpreter before running an op. Stack items which didn't change since the
+/// previous snapshot are shared with it, so long scripts with deep stacks stay cheap.
+#[derive(Clone, Debug)]
+pub(crate) struct Snapshot {
+    stack: Vec<Arc<StackItem>>,
+    alt_stack: Vec<Arc<StackItem>>,
+    exec_stack: Vec<bool>,
+    p2sh_stack: Option<Arc<[StackItem]>>,
+    instruction_pointer: usize,
+    phase: ScriptPhase,
+    script: Script,
+    usage: ResourceUsage,
+    code_separator: usize,
+    num_sig_script_codes: usize,
+}
+
+impl<E: ECC> ScriptInterpreter<E> {
+    /// Records a snapshot before each op, which is required for `step_back` and `goto`.
+    /// Off by default, as verifying an input never steps back.
+    pub fn with_history(mut self) -> Self {
+        self.record_history = true;
+        self
+    }
+
+    /// Number of ops run so far with `run_next_op`, i.e. how far `step_back` can go.
+    /// Always 0 without `with_history`.
+    pub fn num_steps(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Reverts the last op run with `run_next_op`. Returns false if there is nothing to revert.
+    pub fn step_back(&mut self) -> bool {
+        match self.history.pop() {
+            Some(snapshot) => {
+                self.restore(snapshot);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves to the state after `step` ops, stepping back or running ops as required.
+    /// Stops early if the script finishes or an op fails before reaching `step`. Fails with
+    /// `ScriptError::NoHistory` without `with_history`, as steps are counted by the recorded
+    /// snapshots.
+    pub fn goto(&mut self, step: usize) -> Result<(), ScriptError> {
+        if !self.record_history {
+            return Err(ScriptError::NoHistory);
+        }
+        if step < self.history.len() {
+            self.history.truncate(step + 1);
+            self.step_back();
+            return Ok(());
+        }
+        while self.history.len() < step && !self.is_finished() {
+            self.run_next_op()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn record_snapshot(&mut self) {
+        let prev = self.history.last();
+        let snapshot = Snapshot {
+            stack: share_items(&self.stack, prev.map(|prev| &prev.stack[..])),
+            alt_stack: share_items(&self.alt_stack, prev.map(|prev| &prev.alt_stack[..])),
+            exec_stack: self.exec_stack.clone(),
+            p2sh_stack: match (&self.p2sh_stack, prev) {
+                (Some(_), Some(Snapshot { p2sh_stack: Some(shared), .. })) => Some(Arc::clone(shared)),
+                (p2sh_stack, _) => p2sh_stack.as_ref().map(|stack| stack.clone().into()),
+            },
+            instruction_pointer: self.instruction_pointer,
+            phase: self.phase,
+            script: self.script.clone(),
+            usage: self.usage,
+            code_separator: self.code_separator,
+            num_sig_script_codes: self.sig_script_codes.len(),
+        };
+        self.history.push(snapshot);
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.stack = unshare_items(&snapshot.stack);
+        self.alt_stack = unshare_items(&snapshot.alt_stack);
+        self.exec_stack = snapshot.exec_stack;
+        self.p2sh_stack = snapshot.p2sh_stack.map(|stack| stack.to_vec());
+        self.instruction_pointer = snapshot.instruction_pointer;
+        self.phase = snapshot.phase;
+        self.script = snapshot.script;
+        self.usage = snapshot.usage;
+        self.code_separator = snapshot.code_separator;
+        self.sig_script_codes.truncate(snapshot.num_sig_script_codes);
+    }
+}
+
+/// Wraps the items in `Arc`s, reusing the ones of `prev` for items at the same position
+/// which didn't change.
+fn share_items(items: &[StackItem], prev: Option<&[Arc<StackItem>]>) -> Vec<Arc<StackItem>> {
+    let prev = prev.unwrap_or(&[]);
+    items
+        .iter()
+        .enumerate()
+        .map(|(idx, item)| match prev.get(idx) {
+            Some(prev_item) if **prev_item == *item => Arc::clone(prev_item),
+            _ => Arc::new(item.clone()),
+        })
+        .collect()
+}
+
+fn unshare_items(items: &[Arc<StackItem>]) -> Vec<StackItem> {
+    items.iter().map(|item| StackItem::clone(item)).collect()
+}
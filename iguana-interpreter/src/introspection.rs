@@ -0,0 +1,223 @@
+//! Native introspection opcodes of the May 2022 upgrade. These opcodes are not known to
+//! `Opcode`, so they arrive as `Op::Invalid` and are dispatched from `run_op`.
+
+use bitcoin_cash::{ByteArray, Hashed, StackItemData, StackItemDelta, ECC};
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use crate::{
+    num::script_num_data, p2sh_output_script, serialize_script, split_token_prefix,
+    ScriptError, ScriptInterpreter, StackItem, TokenData, VerifyFlags, MAX_SCRIPT_ELEMENT_SIZE,
+};
+
+pub const OP_INPUTINDEX: u8 = 0xc0;
+pub const OP_ACTIVEBYTECODE: u8 = 0xc1;
+pub const OP_TXVERSION: u8 = 0xc2;
+pub const OP_TXINPUTCOUNT: u8 = 0xc3;
+pub const OP_TXOUTPUTCOUNT: u8 = 0xc4;
+pub const OP_TXLOCKTIME: u8 = 0xc5;
+pub const OP_UTXOVALUE: u8 = 0xc6;
+pub const OP_UTXOBYTECODE: u8 = 0xc7;
+pub const OP_OUTPOINTTXHASH: u8 = 0xc8;
+pub const OP_OUTPOINTINDEX: u8 = 0xc9;
+pub const OP_INPUTBYTECODE: u8 = 0xca;
+pub const OP_INPUTSEQUENCENUMBER: u8 = 0xcb;
+pub const OP_OUTPUTVALUE: u8 = 0xcc;
+pub const OP_OUTPUTBYTECODE: u8 = 0xcd;
+pub const OP_UTXOTOKENCATEGORY: u8 = 0xce;
+pub const OP_UTXOTOKENCOMMITMENT: u8 = 0xcf;
+pub const OP_UTXOTOKENAMOUNT: u8 = 0xd0;
+pub const OP_OUTPUTTOKENCATEGORY: u8 = 0xd1;
+pub const OP_OUTPUTTOKENCOMMITMENT: u8 = 0xd2;
+pub const OP_OUTPUTTOKENAMOUNT: u8 = 0xd3;
+
+/// Name of a native introspection opcode, `None` if `code` isn't one.
+pub fn introspection_opcode_name(code: u8) -> Option<&'static str> {
+    Some(match code {
+        OP_INPUTINDEX => "OP_INPUTINDEX",
+        OP_ACTIVEBYTECODE => "OP_ACTIVEBYTECODE",
+        OP_TXVERSION => "OP_TXVERSION",
+        OP_TXINPUTCOUNT => "OP_TXINPUTCOUNT",
+        OP_TXOUTPUTCOUNT => "OP_TXOUTPUTCOUNT",
+        OP_TXLOCKTIME => "OP_TXLOCKTIME",
+        OP_UTXOVALUE => "OP_UTXOVALUE",
+        OP_UTXOBYTECODE => "OP_UTXOBYTECODE",
+        OP_OUTPOINTTXHASH => "OP_OUTPOINTTXHASH",
+        OP_OUTPOINTINDEX => "OP_OUTPOINTINDEX",
+        OP_INPUTBYTECODE => "OP_INPUTBYTECODE",
+        OP_INPUTSEQUENCENUMBER => "OP_INPUTSEQUENCENUMBER",
+        OP_OUTPUTVALUE => "OP_OUTPUTVALUE",
+        OP_OUTPUTBYTECODE => "OP_OUTPUTBYTECODE",
+        OP_UTXOTOKENCATEGORY => "OP_UTXOTOKENCATEGORY",
+        OP_UTXOTOKENCOMMITMENT => "OP_UTXOTOKENCOMMITMENT",
+        OP_UTXOTOKENAMOUNT => "OP_UTXOTOKENAMOUNT",
+        OP_OUTPUTTOKENCATEGORY => "OP_OUTPUTTOKENCATEGORY",
+        OP_OUTPUTTOKENCOMMITMENT => "OP_OUTPUTTOKENCOMMITMENT",
+        OP_OUTPUTTOKENAMOUNT => "OP_OUTPUTTOKENAMOUNT",
+        _ => return None,
+    })
+}
+
+impl<E: ECC> ScriptInterpreter<E> {
+    /// Runs the native introspection opcode `code` against the interpreter's tx.
+    pub(crate) fn run_introspection(&mut self, code: u8) -> Result<(), ScriptError> {
+        let op_name = introspection_opcode_name(code).ok_or(ScriptError::InvalidOpcode(code))?;
+        if code >= OP_UTXOTOKENCATEGORY && !self.flags.contains(VerifyFlags::TOKENS) {
+            return Err(ScriptError::InvalidOpcode(code));
+        }
+        self.require_tx_context()?;
+        let tx = Arc::clone(&self.tx);
+        let (data, name) = match code {
+            OP_INPUTINDEX => (script_num_data(self.input_idx as i64), "input_index".to_string()),
+            OP_ACTIVEBYTECODE => {
+                (self.bytecode(self.script_code()?)?, "active_bytecode".to_string())
+            }
+            OP_TXVERSION => (script_num_data(tx.version() as i64), "tx.version".to_string()),
+            OP_TXINPUTCOUNT => (script_num_data(tx.inputs().len() as i64), "tx.input_count".to_string()),
+            OP_TXOUTPUTCOUNT => {
+                (script_num_data(tx.outputs().len() as i64), "tx.output_count".to_string())
+            }
+            OP_TXLOCKTIME => (script_num_data(tx.lock_time() as i64), "tx.lock_time".to_string()),
+            OP_UTXOVALUE => {
+                let idx = self.pop_input_idx(op_name)?;
+                let value = tx.inputs()[idx].value.ok_or(ScriptError::MissingUtxo(idx))?;
+                (script_num_data(value as i64), format!("utxo[{}].value", idx))
+            }
+            OP_UTXOBYTECODE => {
+                let idx = self.pop_input_idx(op_name)?;
+                let input = &tx.inputs()[idx];
+                let lock_script = input.lock_script.as_ref().ok_or(ScriptError::MissingUtxo(idx))?;
+                let bytecode = if input.is_p2sh == Some(true) {
+                    serialize_script(&p2sh_output_script(self.p2sh_hash(idx)?))?
+                } else {
+                    self.locking_bytecode(serialize_script(lock_script)?)
+                };
+                (self.bytecode(bytecode)?, format!("utxo[{}].bytecode", idx))
+            }
+            OP_OUTPOINTTXHASH => {
+                let idx = self.pop_input_idx(op_name)?;
+                let tx_hash = tx.inputs()[idx].prev_out.tx_hash.clone().into_byte_array();
+                (StackItemData::ByteArray(tx_hash), format!("input[{}].outpoint.tx_hash", idx))
+            }
+            OP_OUTPOINTINDEX => {
+                let idx = self.pop_input_idx(op_name)?;
+                let vout = tx.inputs()[idx].prev_out.vout;
+                (script_num_data(vout as i64), format!("input[{}].outpoint.index", idx))
+            }
+            OP_INPUTBYTECODE => {
+                let idx = self.pop_input_idx(op_name)?;
+                let bytecode = serialize_script(&tx.inputs()[idx].script)?;
+                (self.bytecode(bytecode)?, format!("input[{}].bytecode", idx))
+            }
+            OP_INPUTSEQUENCENUMBER => {
+                let idx = self.pop_input_idx(op_name)?;
+                let sequence = tx.inputs()[idx].sequence;
+                (script_num_data(sequence as i64), format!("input[{}].sequence", idx))
+            }
+            OP_OUTPUTVALUE => {
+                let idx = self.pop_output_idx(op_name)?;
+                let value = tx.outputs()[idx].value;
+                (script_num_data(value as i64), format!("output[{}].value", idx))
+            }
+            OP_OUTPUTBYTECODE => {
+                let idx = self.pop_output_idx(op_name)?;
+                let bytecode = self.locking_bytecode(serialize_script(&tx.outputs()[idx].script)?);
+                (self.bytecode(bytecode)?, format!("output[{}].bytecode", idx))
+            }
+            OP_UTXOTOKENCATEGORY | OP_UTXOTOKENCOMMITMENT | OP_UTXOTOKENAMOUNT => {
+                let idx = self.pop_input_idx(op_name)?;
+                let token = self.tokens.inputs.get(idx).cloned().flatten();
+                let data = token_data(code - OP_UTXOTOKENCATEGORY, token.as_ref());
+                (data, format!("utxo[{}].{}", idx, token_field_name(code - OP_UTXOTOKENCATEGORY)))
+            }
+            OP_OUTPUTTOKENCATEGORY | OP_OUTPUTTOKENCOMMITMENT | OP_OUTPUTTOKENAMOUNT => {
+                let idx = self.pop_output_idx(op_name)?;
+                let token = self.tokens.outputs.get(idx).cloned().flatten();
+                let data = token_data(code - OP_OUTPUTTOKENCATEGORY, token.as_ref());
+                (data, format!("output[{}].{}", idx, token_field_name(code - OP_OUTPUTTOKENCATEGORY)))
+            }
+            _ => return Err(ScriptError::InvalidOpcode(code)),
+        };
+        let name = Some(Arc::new(Cow::Owned(name)));
+        let data = match data {
+            StackItemData::ByteArray(array) => {
+                self.usage.max_element_size = self.usage.max_element_size.max(array.len());
+                StackItemData::ByteArray(array.named_option(name.clone()))
+            }
+            data => data,
+        };
+        self.stack.push(StackItem {
+            data,
+            name,
+            delta: StackItemDelta::Added,
+            sig_scheme: None,
+        });
+        Ok(())
+    }
+
+    /// Strips the token prefix off locking bytecode if tokens are enabled.
+    fn locking_bytecode(&self, bytecode: ByteArray) -> ByteArray {
+        if !self.flags.contains(VerifyFlags::TOKENS) {
+            return bytecode;
+        }
+        match split_token_prefix(&bytecode) {
+            Ok((Some(_), remaining)) => remaining.to_vec().into(),
+            _ => bytecode,
+        }
+    }
+
+    /// Wraps a pushed bytecode, which is subject to the stack item size limit.
+    fn bytecode(&self, bytecode: ByteArray) -> Result<StackItemData, ScriptError> {
+        if self.enforces_limits() && bytecode.len() > MAX_SCRIPT_ELEMENT_SIZE {
+            return Err(ScriptError::PushSize(bytecode.len()));
+        }
+        Ok(StackItemData::ByteArray(bytecode))
+    }
+
+    fn pop_input_idx(&mut self, op_name: &'static str) -> Result<usize, ScriptError> {
+        let idx = self.pop_introspection_idx(op_name)?;
+        let num_inputs = self.tx.inputs().len();
+        match usize::try_from(idx) {
+            Ok(idx) if idx < num_inputs => Ok(idx),
+            _ => Err(ScriptError::InvalidInputIndex { index: idx, num_inputs }),
+        }
+    }
+
+    fn pop_output_idx(&mut self, op_name: &'static str) -> Result<usize, ScriptError> {
+        let idx = self.pop_introspection_idx(op_name)?;
+        let num_outputs = self.tx.outputs().len();
+        match usize::try_from(idx) {
+            Ok(idx) if idx < num_outputs => Ok(idx),
+            _ => Err(ScriptError::InvalidOutputIndex { index: idx, num_outputs }),
+        }
+    }
+
+    fn pop_introspection_idx(&mut self, op_name: &'static str) -> Result<i32, ScriptError> {
+        let item = self
+            .stack
+            .pop()
+            .ok_or(ScriptError::IntrospectionStackEmpty(op_name))?;
+        Ok(self.item_to_int(item)?.value())
+    }
+}
+
+/// Field of the token data pushed by the token introspection opcodes, which come in groups of
+/// category, commitment and amount.
+fn token_data(field: u8, token: Option<&TokenData>) -> StackItemData {
+    match (field, token) {
+        (0, Some(token)) => StackItemData::ByteArray(token.category_with_capability()),
+        (1, Some(token)) => StackItemData::ByteArray(token.commitment()),
+        (2, Some(token)) => script_num_data(token.amount as i64),
+        (2, None) => script_num_data(0),
+        _ => StackItemData::ByteArray(Vec::new().into()),
+    }
+}
+
+fn token_field_name(field: u8) -> &'static str {
+    match field {
+        0 => "token_category",
+        1 => "token_commitment",
+        _ => "token_amount",
+    }
+}
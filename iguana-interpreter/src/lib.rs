@@ -1,27 +1,129 @@
 #[macro_use]
 extern crate thiserror;
+#[macro_use]
+extern crate bitflags;
+
+mod asm;
+mod breakpoint;
+mod flags;
+mod history;
+mod introspection;
+mod limits;
+mod mock;
+mod num;
+mod p2sh;
+mod schnorr;
+mod sig_encoding;
+mod token;
+mod trace;
+mod verification;
+mod verifier;
+
+#[cfg(test)]
+mod tests;
 
+pub use asm::*;
+pub use breakpoint::*;
+pub use flags::*;
+pub use introspection::*;
+pub use limits::*;
+pub use mock::*;
+pub use num::*;
+pub use p2sh::*;
+pub use schnorr::*;
+pub use sig_encoding::*;
+pub use token::*;
+pub use trace::*;
+pub use verification::*;
+pub use verifier::*;
+
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::sync::Arc;
 
 use bitcoin_cash::{
-    encoding_utils::{encode_bool, encode_int, vec_to_int, encode_minimally},
-    Function, Hashed, Integer, Op, Opcode, Ops, Script, SigHashFlags, StackItemData,
+    encoding_utils::{encode_bool, encode_int},
+    deserialize_ops, serialize_ops, Function, Hashed, Integer, Op, Opcode, Script, SigHashFlags, StackItemData,
     StackItemDelta, TaggedOp, Tx, IntegerError, BitcoinCode, error::Error,
     ByteArray, Hash160, Ripemd160, Sha1, Sha256, Sha256d, ECC,
 };
 use std::convert::TryInto;
 
+const MAX_PUBKEYS_PER_MULTISIG: i32 = 20;
+
+const LOCKTIME_THRESHOLD: i64 = 500_000_000;
+const SEQUENCE_FINAL: u32 = 0xffff_ffff;
+
+const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1 << 31;
+const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
+const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000_ffff;
+
+const SIGHASH_FORKID_BIT: u8 = 0x40;
+const SIGHASH_ANYONECANPAY_BIT: u8 = 0x80;
+
 pub struct ScriptInterpreter<E: ECC> {
     stack: Vec<StackItem>,
     alt_stack: Vec<StackItem>,
     tx: Arc<Tx>,
     lock_script: Script,
+    script: Script,
+    phase: ScriptPhase,
+    p2sh_stack: Option<Vec<StackItem>>,
     instruction_pointer: usize,
     exec_stack: Vec<bool>,
     ecc: Arc<E>,
     input_idx: usize,
     is_p2sh: bool,
+    flags: VerifyFlags,
+    usage: ResourceUsage,
+    code_separator: usize,
+    sig_script_codes: Vec<SigScriptCode>,
+    tokens: TxTokens,
+    p2sh_hashes: P2shHashes,
+    history: Vec<history::Snapshot>,
+    record_history: bool,
+    breakpoints: Vec<(BreakpointId, Breakpoint)>,
+    next_breakpoint_id: BreakpointId,
+    has_tx_context: bool,
+}
+
+/// The script currently being evaluated by the interpreter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScriptPhase {
+    /// The input script, pushing the data the lock script consumes. For P2SH inputs evaluated
+    /// without the P2SH flag, the redeem script push is skipped.
+    InputScript,
+    /// The P2SH output script, `OP_HASH160 <hash> OP_EQUAL`, checking the redeem script's hash.
+    P2shHashCheck,
+    /// The lock script, which is the redeem script for P2SH inputs.
+    LockScript,
+}
+
+/// The scriptCode a signature check of the lock script committed to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SigScriptCode {
+    /// Index of the signature checking op in the lock script.
+    pub instruction_pointer: usize,
+    /// Index of the first op of the scriptCode, i.e. after the last executed OP_CODESEPARATOR.
+    pub code_separator: usize,
+    /// The serialized scriptCode used in the sighash preimage.
+    pub script_code: ByteArray,
+}
+
+/// Why a `ScriptInterpreter` couldn't be constructed for an input.
+#[derive(Error, Clone, Debug)]
+pub enum ConstructionError {
+    #[error("Input index {input_idx} out of range, tx has {num_inputs} inputs")]
+    InputIndexOutOfRange { input_idx: usize, num_inputs: usize },
+
+    #[error("Input {0} has no lock script, the UTXO it spends is unknown")]
+    MissingLockScript(usize),
+
+    #[error("Input {0} doesn't specify whether it spends a P2SH output")]
+    MissingIsP2sh(usize),
+
+    #[error("Lock script of input {0} is invalid: {1}")]
+    InvalidLockScript(usize, Cow<'static, str>),
 }
 
 #[derive(Error, Clone, Debug)]
@@ -35,12 +137,87 @@ pub enum ScriptError {
     #[error("Invalid signature: {0}, {1}")]
     InvalidSignature(ByteArray, ByteArray),
 
+    #[error("Signature not strictly DER encoded: {0}")]
+    NonDerSignature(ByteArray),
+
+    #[error("Signature has a high S value: {0}")]
+    HighSSignature(ByteArray),
+
+    #[error("Undefined sighash type: {0:#04x}")]
+    UndefinedSigHashType(u8),
+
+    #[error("Sighash type {0:#04x} uses SIGHASH_FORKID, but it is not enabled")]
+    IllegalForkId(u8),
+
+    #[error("Sighash type {0:#04x} must use SIGHASH_FORKID")]
+    MustUseForkId(u8),
+
     #[error("OP_EQUALVERIFY failed: {0} ≠ {1}")]
     EqualVerifyFailed(ByteArray, ByteArray),
 
     #[error("OP_VERIFY failed")]
     VerifyFailed,
 
+    #[error("OP_CHECKMULTISIGVERIFY failed")]
+    CheckMultiSigVerifyFailed,
+
+    #[error("Invalid public key count: {0}")]
+    InvalidPubKeyCount(Integer),
+
+    #[error("Invalid signature count: {0}")]
+    InvalidSignatureCount(Integer),
+
+    #[error("Invalid multisig bitfield size: expected {expected} bytes, got {actual}")]
+    InvalidBitfieldSize { expected: usize, actual: usize },
+
+    #[error("Multisig bitfield has bits set beyond the {0} public keys")]
+    InvalidBitRange(usize),
+
+    #[error("Multisig bitfield has {actual} bits set, but {expected} signatures were given")]
+    InvalidBitCount { expected: usize, actual: usize },
+
+    #[error("Schnorr signature not allowed in legacy multisig: {0}")]
+    SchnorrSignatureInLegacyMultisig(ByteArray),
+
+    #[error("Invalid Schnorr signature length: {0}")]
+    InvalidSchnorrSignatureLength(ByteArray),
+
+    #[error("Failed multisig check with non-null signatures")]
+    MultiSigNullFail,
+
+    #[error("OP_CHECKMULTISIG dummy element must be empty: {0}")]
+    NonNullDummy(ByteArray),
+
+    #[error("Push not minimally encoded: {0}")]
+    NonMinimalPush(ByteArray),
+
+    #[error("Input script contains non-push op: {0:?}")]
+    SigPushOnly(Op),
+
+    #[error("P2SH redeem script hash doesn't match the hash of the output")]
+    P2shHashMismatch,
+
+    #[error("Invalid redeem script: {0}")]
+    InvalidRedeemScript(Cow<'static, str>),
+
+    #[error("{0}: Upgradable NOPs are discouraged")]
+    DiscourageUpgradableNops(Opcode),
+
+    #[error("Stack size limit exceeded: {0} items")]
+    StackSize(usize),
+
+    #[error("Stack item size limit exceeded: {0} bytes")]
+    PushSize(usize),
+
+    #[error("Script size limit exceeded: {0} bytes")]
+    ScriptSize(usize),
+
+    #[error("Op count limit exceeded: {0} ops")]
+    OpCount(usize),
+
+    #[error("Input SigChecks limit exceeded: {sig_checks} SigChecks, but at most {limit} allowed")]
+    InputSigChecks { sig_checks: usize, limit: usize },
+
     #[error("Opcode not implemented")]
     NotImplemented,
 
@@ -50,8 +227,11 @@ pub enum ScriptError {
     #[error("Invalid data type")]
     InvalidDataType,
 
-    #[error("Stack empty")]
-    StackEmpty,
+    #[error("{0}: Stack empty")]
+    StackEmpty(Opcode),
+
+    #[error("{0}: Alt stack empty")]
+    AltStackEmpty(Opcode),
 
     #[error("{0}: {1}")]
     OpcodeMsg(Opcode, Cow<'static, str>),
@@ -59,9 +239,90 @@ pub enum ScriptError {
     #[error("{0}: Unbalanced conditionals")]
     UnbalancedConditionals(Opcode),
 
+    #[error("Script ended with {0} unterminated OP_IF/OP_NOTIF")]
+    UnterminatedConditionals(usize),
+
+    #[error("{0}: Condition must be empty or exactly 0x01: {1}")]
+    MinimalIf(Opcode, ByteArray),
+
     #[error("Invalid opcode: {0}")]
     InvalidOpcode(u8),
 
+    #[error("{0}: Stack empty")]
+    IntrospectionStackEmpty(&'static str),
+
+    #[error("Input index {index} out of range, tx has {num_inputs} inputs")]
+    InvalidInputIndex { index: i32, num_inputs: usize },
+
+    #[error("Output index {index} out of range, tx has {num_outputs} outputs")]
+    InvalidOutputIndex { index: i32, num_outputs: usize },
+
+    #[error("No transaction context: signature checks, lock times and introspection need a tx")]
+    NoTxContext,
+
+    #[error("No history recorded: going to a step requires `with_history`")]
+    NoHistory,
+
+    #[error("UTXO of input {0} unknown, its value and lock script are required")]
+    MissingUtxo(usize),
+
+    #[error("Invalid token prefix: {0}")]
+    InvalidTokenPrefix(Cow<'static, str>),
+
+    #[error("{0}: Result out of 64-bit integer range")]
+    IntegerOverflow(Opcode),
+
+    #[error("{0}: Division by zero")]
+    DivisionByZero(Opcode),
+
+    #[error("{0}: Opcode is disabled")]
+    DisabledOpcode(Opcode),
+
+    #[error("Script number has {0} bytes, but at most {1} are allowed")]
+    ScriptNumOverflow(usize, usize),
+
+    #[error("Script number not minimally encoded: {0}")]
+    NonMinimalScriptNum(ByteArray),
+
+    #[error("{0}: Negative lock time: {1}")]
+    NegativeLockTime(Opcode, i64),
+
+    #[error(
+        "OP_CHECKLOCKTIMEVERIFY failed: required lock time {required} and tx lock time \
+         {lock_time} differ in type (block height vs. timestamp)"
+    )]
+    LockTimeTypeMismatch { required: i64, lock_time: u32 },
+
+    #[error(
+        "OP_CHECKLOCKTIMEVERIFY failed: required lock time {required} not reached by tx lock \
+         time {lock_time}"
+    )]
+    LockTimeUnsatisfied { required: i64, lock_time: u32 },
+
+    #[error("OP_CHECKLOCKTIMEVERIFY failed: input sequence is final, lock time is disabled")]
+    LockTimeInputFinal,
+
+    #[error("OP_CHECKSEQUENCEVERIFY requires tx version 2 or higher, but version is {0}")]
+    SequenceTxVersion(i32),
+
+    #[error("OP_CHECKSEQUENCEVERIFY failed: input sequence {0:#010x} has the disable flag set")]
+    SequenceDisabled(u32),
+
+    #[error(
+        "OP_CHECKSEQUENCEVERIFY failed: required sequence {required:#x} and input sequence \
+         {sequence:#010x} differ in type (blocks vs. 512 second units)"
+    )]
+    SequenceTypeMismatch { required: i64, sequence: u32 },
+
+    #[error(
+        "OP_CHECKSEQUENCEVERIFY failed: required sequence {required:#x} not reached by input \
+         sequence {sequence:#010x}"
+    )]
+    SequenceUnsatisfied { required: i64, sequence: u32 },
+
+    #[error("Cannot construct interpreter: {0}")]
+    Construction(#[from] ConstructionError),
+
     #[error("Invalid depth: {0}")]
     InvalidDepth(Integer),
 
@@ -77,104 +338,397 @@ pub struct StackItem {
     pub data: StackItemData,
     pub name: Option<Arc<Cow<'static, str>>>,
     pub delta: StackItemDelta,
+    /// Signature scheme used by the signature check which pushed this item, if any.
+    pub sig_scheme: Option<SignatureScheme>,
 }
 impl StackItem {
+    /// Consensus boolean value of the item, see `cast_to_bool`.
     pub fn to_bool(&self) -> bool {
         match self.data {
             StackItemData::Integer(int) => int != Integer::ZERO,
             StackItemData::Boolean(boolean) => boolean,
-            StackItemData::ByteArray(ref array) => array.len() > 0,
+            StackItemData::ByteArray(ref array) => cast_to_bool(array),
         }
     }
 }
 
 impl<E: ECC> ScriptInterpreter<E> {
-    pub fn new(tx: Arc<Tx>, input_idx: usize, ecc: Arc<E>) -> Self {
-        let input = &tx.inputs()[input_idx];
-        ScriptInterpreter {
+    /// Interpreter for input `input_idx` of `tx`, which needs the lock script of the UTXO it
+    /// spends and whether it is P2SH. With the TOKENS flag, a token prefix of the lock script
+    /// is split off, and only the remaining script is evaluated and signed. Redeem scripts of
+    /// P2SH inputs are run as they are, the tokens of their UTXOs are set via `with_tokens`.
+    pub fn new(
+        tx: Arc<Tx>,
+        input_idx: usize,
+        ecc: Arc<E>,
+        flags: VerifyFlags,
+    ) -> Result<Self, ConstructionError> {
+        let input = tx.inputs().get(input_idx).ok_or(ConstructionError::InputIndexOutOfRange {
+            input_idx,
+            num_inputs: tx.inputs().len(),
+        })?;
+        let lock_script = input
+            .lock_script
+            .clone()
+            .ok_or(ConstructionError::MissingLockScript(input_idx))?;
+        let is_p2sh = input.is_p2sh.ok_or(ConstructionError::MissingIsP2sh(input_idx))?;
+        let mut tokens = TxTokens::from_tx(&tx);
+        let lock_script = if flags.contains(VerifyFlags::TOKENS) && !is_p2sh {
+            let (token, lock_script) = split_script_token(&lock_script)
+                .map_err(|err| ConstructionError::InvalidLockScript(input_idx, err.to_string().into()))?;
+            tokens.inputs[input_idx] = token;
+            lock_script
+        } else {
+            lock_script
+        };
+        // Without P2SH evaluation, the redeem script push is skipped and the lock script is run directly
+        let input_ops = input.script.ops_arc();
+        let num_skipped = if is_p2sh && !flags.contains(VerifyFlags::P2SH) { 1 } else { 0 };
+        let input_script = Script::new(input_ops[..input_ops.len().saturating_sub(num_skipped)].to_vec());
+        Ok(ScriptInterpreter {
             stack: Vec::new(),
             alt_stack: Vec::new(),
             instruction_pointer: 0,
-            lock_script: input
-                .lock_script
-                .clone()
-                .expect("Input must have lock_script"),
-            is_p2sh: input.is_p2sh.expect("Input must have is_p2sh"),
-            tx,
+            script: input_script,
+            phase: ScriptPhase::InputScript,
+            p2sh_stack: None,
+            lock_script,
+            is_p2sh,
             input_idx,
             exec_stack: Vec::new(),
             ecc,
+            flags,
+            usage: ResourceUsage::default(),
+            code_separator: 0,
+            sig_script_codes: Vec::new(),
+            tokens,
+            p2sh_hashes: P2shHashes::default(),
+            history: Vec::new(),
+            record_history: false,
+            breakpoints: Vec::new(),
+            next_breakpoint_id: 0,
+            has_tx_context: true,
+            tx,
+        })
+    }
+
+    /// Replaces the token data read from the tx's scripts, e.g. with token data of the
+    /// spent UTXOs which isn't part of the tx.
+    pub fn with_tokens(mut self, tokens: TxTokens) -> Self {
+        self.tokens = tokens;
+        self
+    }
+
+    pub fn tokens(&self) -> &TxTokens {
+        &self.tokens
+    }
+
+    fn pop(&mut self, opcode: Opcode) -> Result<StackItem, ScriptError> {
+        self.stack.pop().ok_or(ScriptError::StackEmpty(opcode))
+    }
+
+    fn pop_bool(&mut self, opcode: Opcode) -> Result<bool, ScriptError> {
+        Ok(self.pop(opcode)?.to_bool())
+    }
+
+    /// Pops a script number and checks whether it is non-zero, as done by the numeric
+    /// boolean opcodes (OP_NOT, OP_BOOLAND, OP_BOOLOR).
+    fn pop_int_bool(&mut self, opcode: Opcode) -> Result<bool, ScriptError> {
+        Ok(self.pop_num(opcode)? != 0)
+    }
+
+    /// Maximum length of script numbers in bytes, 8 with INTEGERS_64BIT and 4 otherwise.
+    fn max_num_len(&self) -> usize {
+        if self.flags.contains(VerifyFlags::INTEGERS_64BIT) {
+            8
+        } else {
+            4
         }
     }
 
-    fn pop(&mut self) -> Result<StackItem, ScriptError> {
-        if let Some(item) = self.stack.pop() {
-            return Ok(item);
+    /// Pops an operand of the arithmetic opcodes, which is at most `max_num_len` bytes long.
+    fn pop_num(&mut self, opcode: Opcode) -> Result<i64, ScriptError> {
+        match self.pop(opcode)?.data {
+            StackItemData::ByteArray(byte_array) => {
+                let require_minimal = self.flags.contains(VerifyFlags::MINIMALDATA);
+                decode_script_num(&byte_array, self.max_num_len(), require_minimal)
+            }
+            StackItemData::Integer(int) => Ok(int.value() as i64),
+            StackItemData::Boolean(boolean) => Ok(boolean as i64),
         }
-        return Err(ScriptError::StackEmpty);
     }
 
-    fn pop_bool(&mut self) -> Result<bool, ScriptError> {
-        match self.pop()?.data {
-            StackItemData::ByteArray(_) => Err(ScriptError::InvalidDataType),
-            StackItemData::Integer(int) => Ok(int != Integer::ZERO),
-            StackItemData::Boolean(boolean) => Ok(boolean),
+    /// Pushes the result of an arithmetic opcode.
+    fn push_num(&mut self, op: &TaggedOp, value: i64) {
+        self.push_tagged_data(op, script_num_data(value));
+    }
+
+    /// Checks the result of an arithmetic opcode. With INTEGERS_64BIT, results have to be
+    /// representable as 8 byte script number, i.e. within ±(2^63 - 1). Otherwise, results
+    /// of 4 byte operands always fit and may be larger than 4 bytes.
+    fn checked_num(&self, opcode: Opcode, result: Option<i64>) -> Result<i64, ScriptError> {
+        match result {
+            Some(value) if value != i64::MIN => Ok(value),
+            _ => Err(ScriptError::IntegerOverflow(opcode)),
         }
     }
 
-    fn pop_int(&mut self) -> Result<Integer, ScriptError> {
-        match self.pop()?.data {
+    fn pop_int(&mut self, opcode: Opcode) -> Result<Integer, ScriptError> {
+        let item = self.pop(opcode)?;
+        self.item_to_int(item)
+    }
+
+    fn item_to_int(&self, item: StackItem) -> Result<Integer, ScriptError> {
+        match item.data {
             StackItemData::ByteArray(byte_array) => {
-                let mut minimally_encoded = byte_array.to_vec();
-                encode_minimally(&mut minimally_encoded);
-                if byte_array.as_ref() != minimally_encoded.as_slice() {
-                    return Err(ScriptError::InvalidDataType);
-                }
-                vec_to_int(&minimally_encoded).and_then(|int| Ok(Integer::new(int)?)).map_err(|_| ScriptError::InvalidDataType)
+                let require_minimal = self.flags.contains(VerifyFlags::MINIMALDATA);
+                Ok(Integer::new(decode_script_num(&byte_array, self.max_num_len(), require_minimal)?)?)
             },
             StackItemData::Integer(int) => Ok(int),
             StackItemData::Boolean(boolean) => Ok(Integer::new(if boolean { 1 } else { 0 }).unwrap()),
         }
     }
 
-    fn pop_byte_array(&mut self) -> Result<ByteArray, ScriptError> {
-        match self.pop()?.data {
+    /// Pops the condition of OP_IF/OP_NOTIF, which has to be minimal under MINIMALIF.
+    fn pop_condition(&mut self, opcode: Opcode) -> Result<bool, ScriptError> {
+        if self.flags.contains(VerifyFlags::MINIMALIF) {
+            let condition = self.pop_byte_array(opcode)?;
+            if condition.len() > 1 || (condition.len() == 1 && condition[0] != 0x01) {
+                return Err(ScriptError::MinimalIf(opcode, condition));
+            }
+            return Ok(condition.len() == 1);
+        }
+        self.pop_bool(opcode)
+    }
+
+    fn peek_script_num(&self, opcode: Opcode, max_len: usize) -> Result<i64, ScriptError> {
+        match &self.stack.last().ok_or(ScriptError::StackEmpty(opcode))?.data {
+            StackItemData::ByteArray(byte_array) => {
+                decode_script_num(byte_array, max_len, self.flags.contains(VerifyFlags::MINIMALDATA))
+            }
+            StackItemData::Integer(int) => Ok(int.value() as i64),
+            StackItemData::Boolean(boolean) => Ok(if *boolean { 1 } else { 0 }),
+        }
+    }
+
+    fn pop_byte_array(&mut self, opcode: Opcode) -> Result<ByteArray, ScriptError> {
+        match self.pop(opcode)?.data {
             StackItemData::ByteArray(byte_array) => Ok(byte_array),
             StackItemData::Integer(int) => Ok(encode_int(int.value()).into()),
             StackItemData::Boolean(boolean) => Ok(encode_bool(boolean).into()),
         }
     }
 
-    pub fn run(&mut self) -> Result<bool, ScriptError> {
+    /// Runs the remaining ops and checks whether the input is valid.
+    pub fn run(&mut self) -> VerificationResult {
         while !self.is_finished() {
-            self.run_next_op()?;
+            if let Err(err) = self.run_next_op() {
+                return VerificationResult::failed(self.stack.clone(), err);
+            }
+        }
+        let top = match self.stack.last() {
+            Some(top) => top,
+            None => return VerificationResult::failed(Vec::new(), VerificationFailure::StackEmpty),
+        };
+        if !top.to_bool() {
+            return VerificationResult::failed(self.stack.clone(), VerificationFailure::TopOfStackFalse);
         }
-        Ok(self.stack[0].to_bool())
+        if self.flags.contains(VerifyFlags::CLEANSTACK) && self.stack.len() != 1 {
+            let failure = VerificationFailure::StackNotClean(self.stack.len());
+            return VerificationResult::failed(self.stack.clone(), failure);
+        }
+        VerificationResult::succeeded(self.stack.clone())
     }
 
+    /// Runs the remaining ops of the input script, after which the lock script
+    /// (or the P2SH hash check) is next.
     pub fn push_input_data(&mut self) -> Result<(), ScriptError> {
-        let input_script = Arc::clone(self.tx.inputs()[self.input_idx].script.ops_arc());
-        for op in &input_script[..input_script.len() - if self.is_p2sh {1} else {0}] {
-            self.run_op(op)?;
+        while self.phase == ScriptPhase::InputScript {
+            self.run_next_op()?;
+        }
+        Ok(())
+    }
+
+    /// Checks the size of the whole input script and whether it only contains pushes,
+    /// before its first op is run.
+    fn check_input_script(&mut self) -> Result<(), ScriptError> {
+        let tx = Arc::clone(&self.tx);
+        let input_script = &tx.inputs()[self.input_idx].script;
+        self.check_script_size(input_script)?;
+        let evaluate_p2sh = self.is_p2sh && self.flags.contains(VerifyFlags::P2SH);
+        if self.flags.contains(VerifyFlags::SIGPUSHONLY) || evaluate_p2sh {
+            if let Some(op) = input_script.ops_arc().iter().find(|op| !is_push_op(&op.op)) {
+                return Err(ScriptError::SigPushOnly(op.op.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Continues with the P2SH hash check or the lock script once the input script is done.
+    fn finish_input_script(&mut self) -> Result<(), ScriptError> {
+        let evaluate_p2sh = self.is_p2sh && self.flags.contains(VerifyFlags::P2SH);
+        if evaluate_p2sh {
+            self.p2sh_stack = Some(self.stack.clone());
+            let output_script = p2sh_output_script(self.p2sh_hash(self.input_idx)?);
+            self.begin_script(output_script, ScriptPhase::P2shHashCheck)?;
+        } else {
+            self.begin_script(self.lock_script.clone(), ScriptPhase::LockScript)?;
         }
         Ok(())
     }
 
+    /// Starts evaluating the given script, resetting the per-script op count. Like every
+    /// script evaluation of a node, each script starts with an empty alt stack.
+    fn begin_script(&mut self, script: Script, phase: ScriptPhase) -> Result<(), ScriptError> {
+        self.check_script_size(&script)?;
+        self.alt_stack.clear();
+        self.script = script;
+        self.phase = phase;
+        self.instruction_pointer = 0;
+        self.code_separator = 0;
+        self.usage.op_count = 0;
+        Ok(())
+    }
+
+    /// Completes the P2SH hash check and continues with the redeem script
+    /// on the stack as it was after the input script.
+    fn finish_p2sh_hash_check(&mut self) -> Result<(), ScriptError> {
+        if !self.pop(Opcode::OP_EQUAL)?.to_bool() {
+            return Err(ScriptError::P2shHashMismatch);
+        }
+        self.stack = self.p2sh_stack.take().unwrap_or_default();
+        let redeem_script = self.pop_byte_array(Opcode::OP_HASH160)?;
+        let lock_script_ser = serialize_script(&self.lock_script)?;
+        // Prefer the given lock script, as it carries the op names and source locations
+        let script = if redeem_script.data() == lock_script_ser.data() {
+            self.lock_script.clone()
+        } else {
+            let ops = deserialize_ops(&redeem_script)
+                .map_err(|err| ScriptError::InvalidRedeemScript(err.to_string().into()))?;
+            Script::from_ops(ops)
+        };
+        self.begin_script(script, ScriptPhase::LockScript)
+    }
+
+    fn enforces_limits(&self) -> bool {
+        self.flags.contains(VerifyFlags::ENFORCE_LIMITS)
+    }
+
+    fn check_script_size(&mut self, script: &Script) -> Result<(), ScriptError> {
+        let script_size = serialize_script(script)?.len();
+        self.usage.max_script_size = self.usage.max_script_size.max(script_size);
+        if self.enforces_limits() && script_size > MAX_SCRIPT_SIZE {
+            return Err(ScriptError::ScriptSize(script_size));
+        }
+        Ok(())
+    }
+
+    fn add_op_count(&mut self, num_ops: usize) -> Result<(), ScriptError> {
+        self.usage.op_count += num_ops;
+        if self.enforces_limits() && self.usage.op_count > MAX_OPS_PER_SCRIPT {
+            return Err(ScriptError::OpCount(self.usage.op_count));
+        }
+        Ok(())
+    }
+
+    fn add_sig_checks(&mut self, num_sig_checks: usize) -> Result<(), ScriptError> {
+        self.usage.sig_checks += num_sig_checks;
+        if self.enforces_limits() {
+            let input_script = &self.tx.inputs()[self.input_idx].script;
+            let limit = max_input_sig_checks(serialize_script(input_script)?.len());
+            if self.usage.sig_checks > limit {
+                return Err(ScriptError::InputSigChecks {
+                    sig_checks: self.usage.sig_checks,
+                    limit,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn check_stack_limits(&mut self) -> Result<(), ScriptError> {
+        let stack_depth = self.stack.len() + self.alt_stack.len();
+        self.usage.max_stack_depth = self.usage.max_stack_depth.max(stack_depth);
+        if self.enforces_limits() {
+            if stack_depth > MAX_STACK_SIZE {
+                return Err(ScriptError::StackSize(stack_depth));
+            }
+            if self.usage.max_element_size > MAX_SCRIPT_ELEMENT_SIZE {
+                return Err(ScriptError::PushSize(self.usage.max_element_size));
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of non-push ops in the current script, limited to 201.
+    pub fn op_count(&self) -> usize {
+        self.usage.op_count
+    }
+
+    /// Number of signature checks in this input so far.
+    pub fn sig_checks(&self) -> usize {
+        self.usage.sig_checks
+    }
+
+    pub fn resource_usage(&self) -> ResourceUsage {
+        self.usage
+    }
+
+    pub fn flags(&self) -> VerifyFlags {
+        self.flags
+    }
+
     pub fn is_finished(&self) -> bool {
-        self.instruction_pointer >= self.lock_script.ops().len()
+        self.phase == ScriptPhase::LockScript
+            && self.instruction_pointer >= self.script.ops_arc().len()
     }
 
     pub fn instruction_pointer(&self) -> usize {
         self.instruction_pointer
     }
 
+    pub fn phase(&self) -> ScriptPhase {
+        self.phase
+    }
+
+    /// The script of the current phase, which `instruction_pointer` refers to.
+    pub fn script(&self) -> &Script {
+        &self.script
+    }
+
+    /// Runs the op at the instruction pointer and moves on to the next phase after the
+    /// last op of a script. An empty input script takes a step without op.
     pub fn run_next_op(&mut self) -> Result<(), ScriptError> {
-        if self.instruction_pointer >= self.lock_script.ops().len() {
+        if self.is_finished() {
             return Err(ScriptError::ScriptFinished);
         }
-        let ops = Arc::clone(self.lock_script.ops_arc());
-        self.run_op(&ops[self.instruction_pointer])?;
-        self.instruction_pointer += 1;
+        if self.record_history {
+            self.record_snapshot();
+        }
+        if self.phase == ScriptPhase::InputScript && self.instruction_pointer == 0 {
+            self.check_input_script()?;
+        }
+        let ops = Arc::clone(self.script.ops_arc());
+        if let Some(op) = ops.get(self.instruction_pointer) {
+            self.run_op(op)?;
+            self.instruction_pointer += 1;
+        }
+        if self.instruction_pointer >= ops.len() {
+            self.check_conditionals_terminated()?;
+            match self.phase {
+                ScriptPhase::InputScript => self.finish_input_script()?,
+                ScriptPhase::P2shHashCheck => self.finish_p2sh_hash_check()?,
+                ScriptPhase::LockScript => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Each script has to close all of its OP_IF/OP_NOTIF branches.
+    fn check_conditionals_terminated(&self) -> Result<(), ScriptError> {
+        if !self.exec_stack.is_empty() {
+            return Err(ScriptError::UnterminatedConditionals(self.exec_stack.len()));
+        }
         Ok(())
     }
 
@@ -202,16 +756,26 @@ impl<E: ECC> ScriptInterpreter<E> {
             .flatten()
             .map(Arc::new);
         if let StackItemData::ByteArray(array) = data {
+            self.usage.max_element_size = self.usage.max_element_size.max(array.len());
             data = StackItemData::ByteArray(array.named_option(name.clone()));
         }
         let delta = match &op.op {
+            // the behavior of multisig isn't expressible as OpcodeBehavior, it only pushes its result
+            Op::Code(Opcode::OP_CHECKMULTISIG) => StackItemDelta::Added,
+            // disabled opcodes have no behavior, OP_MUL is re-enabled with 64-bit integers
+            Op::Code(Opcode::OP_MUL) => StackItemDelta::Added,
             Op::Code(opcode) => *opcode.behavior().delta.get(idx).unwrap_or(&StackItemDelta::Removed),
             Op::PushBoolean(_) | Op::PushByteArray { .. } | Op::PushInteger(_) => {
                 StackItemDelta::Added
             }
             Op::Invalid(_) => StackItemDelta::Untouched,
         };
-        self.stack.push(StackItem { data, name, delta })
+        self.stack.push(StackItem {
+            data,
+            name,
+            delta,
+            sig_scheme: None,
+        })
     }
 
     fn run_op(&mut self, op: &TaggedOp) -> Result<(), ScriptError> {
@@ -220,8 +784,22 @@ impl<E: ECC> ScriptInterpreter<E> {
             .for_each(|stack| stack.delta = StackItemDelta::Untouched);
 
         let is_executed = self.exec_stack.iter().all(|&x| x);
+        match op.op {
+            Op::Code(opcode) if opcode as u8 > Opcode::OP_16 as u8 => self.add_op_count(1)?,
+            Op::Invalid(code) if code > Opcode::OP_16 as u8 => self.add_op_count(1)?,
+            Op::PushByteArray { ref array, .. }
+                if self.enforces_limits() && array.len() > MAX_SCRIPT_ELEMENT_SIZE =>
+            {
+                return Err(ScriptError::PushSize(array.len()));
+            }
+            _ => {}
+        }
         use Opcode::*;
-        if let Op::Code(OP_IF) | Op::Code(OP_ELSE) | Op::Code(OP_ENDIF) = &op.op {
+        // disabled opcodes fail the script even in unexecuted branches
+        if matches!(op.op, Op::Code(OP_MUL)) && !self.flags.contains(VerifyFlags::INTEGERS_64BIT) {
+            return Err(ScriptError::DisabledOpcode(OP_MUL));
+        }
+        if let Op::Code(OP_IF) | Op::Code(OP_NOTIF) | Op::Code(OP_ELSE) | Op::Code(OP_ENDIF) = &op.op {
         } else {
             if !is_executed {
                 return Ok(());
@@ -236,17 +814,24 @@ impl<E: ECC> ScriptInterpreter<E> {
                 self.push_tagged_data(op, StackItemData::Integer(data));
                 Ok(())
             }
-            Op::PushByteArray { ref array, .. } => {
+            Op::PushByteArray { ref array, is_minimal } => {
+                if self.flags.contains(VerifyFlags::MINIMALDATA) && !is_minimal {
+                    return Err(ScriptError::NonMinimalPush(array.clone()));
+                }
                 self.push_tagged_data(op, StackItemData::ByteArray(array.clone()));
                 Ok(())
             }
             Op::Code(code) => self.run_opcode(op, code, is_executed),
+            Op::Invalid(code) if self.flags.contains(VerifyFlags::NATIVE_INTROSPECTION) => {
+                self.run_introspection(code)
+            }
             Op::Invalid(code) => Err(ScriptError::InvalidOpcode(code)),
-        }
+        }?;
+        self.check_stack_limits()
     }
 
-    fn pop_depth_to_idx(&mut self) -> Result<usize, ScriptError> {
-        let depth = self.pop_int()?;
+    fn pop_depth_to_idx(&mut self, opcode: Opcode) -> Result<usize, ScriptError> {
+        let depth = self.pop_int(opcode)?;
         let depth_usize = depth.value().try_into().map_err(|_| ScriptError::InvalidDepth(depth))?;
         self.stack.len()
             .checked_sub(depth_usize)
@@ -254,6 +839,263 @@ impl<E: ECC> ScriptInterpreter<E> {
             .ok_or(ScriptError::InvalidDepth(depth))
     }
 
+    /// BIP65 check of the required lock time against the tx's lock time.
+    fn check_lock_time(&self, required: i64) -> Result<(), ScriptError> {
+        self.require_tx_context()?;
+        let lock_time = self.tx.lock_time();
+        let is_required_time = required >= LOCKTIME_THRESHOLD;
+        let is_lock_time_time = lock_time as i64 >= LOCKTIME_THRESHOLD;
+        if is_required_time != is_lock_time_time {
+            return Err(ScriptError::LockTimeTypeMismatch { required, lock_time });
+        }
+        if required > lock_time as i64 {
+            return Err(ScriptError::LockTimeUnsatisfied { required, lock_time });
+        }
+        if self.tx.inputs()[self.input_idx].sequence == SEQUENCE_FINAL {
+            return Err(ScriptError::LockTimeInputFinal);
+        }
+        Ok(())
+    }
+
+    /// BIP112 check of the required relative lock time against the input's sequence.
+    fn check_sequence(&self, required: i64) -> Result<(), ScriptError> {
+        self.require_tx_context()?;
+        if (self.tx.version() as u32) < 2 {
+            return Err(ScriptError::SequenceTxVersion(self.tx.version()));
+        }
+        let sequence = self.tx.inputs()[self.input_idx].sequence;
+        if sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+            return Err(ScriptError::SequenceDisabled(sequence));
+        }
+        let mask = SEQUENCE_LOCKTIME_TYPE_FLAG | SEQUENCE_LOCKTIME_MASK;
+        let required_masked = required as u32 & mask;
+        let sequence_masked = sequence & mask;
+        let is_required_time = required_masked >= SEQUENCE_LOCKTIME_TYPE_FLAG;
+        let is_sequence_time = sequence_masked >= SEQUENCE_LOCKTIME_TYPE_FLAG;
+        if is_required_time != is_sequence_time {
+            return Err(ScriptError::SequenceTypeMismatch { required, sequence });
+        }
+        if required_masked > sequence_masked {
+            return Err(ScriptError::SequenceUnsatisfied { required, sequence });
+        }
+        Ok(())
+    }
+
+    fn check_upgradable_nop(&self, opcode: Opcode) -> Result<(), ScriptError> {
+        if self.flags.contains(VerifyFlags::DISCOURAGE_UPGRADABLE_NOPS) {
+            return Err(ScriptError::DiscourageUpgradableNops(opcode));
+        }
+        Ok(())
+    }
+
+    fn check_pubkey_encoding(&self, pubkey: &ByteArray) -> Result<(), ScriptError> {
+        if self.flags.contains(VerifyFlags::STRICTENC) && !is_valid_pubkey_encoding(pubkey) {
+            return Err(ScriptError::InvalidPubKey(pubkey.clone()));
+        }
+        Ok(())
+    }
+
+    /// Checks the encoding of a signature, which includes the sighash byte if `has_sig_hash`
+    /// is set. Schnorr signatures have a fixed length, so only ECDSA signatures are checked
+    /// for DER encoding and low S.
+    fn check_sig_encoding(&self, sig: &ByteArray, has_sig_hash: bool) -> Result<(), ScriptError> {
+        if sig.is_empty() {
+            return Ok(());
+        }
+        let der_sig = if has_sig_hash { &sig[..sig.len() - 1] } else { &sig[..] };
+        if SignatureScheme::of_sig(der_sig) == SignatureScheme::Schnorr {
+            if has_sig_hash {
+                self.check_sig_hash_type(sig)?;
+            }
+            return Ok(());
+        }
+        let der_flags = VerifyFlags::DERSIG | VerifyFlags::LOW_S | VerifyFlags::STRICTENC;
+        if self.flags.intersects(der_flags) && !is_valid_der_encoding(der_sig) {
+            return Err(ScriptError::NonDerSignature(sig.clone()));
+        }
+        if self.flags.contains(VerifyFlags::LOW_S) && !is_low_der_s(der_sig) {
+            return Err(ScriptError::HighSSignature(sig.clone()));
+        }
+        if has_sig_hash {
+            self.check_sig_hash_type(sig)?;
+        }
+        Ok(())
+    }
+
+    fn check_sig_hash_type(&self, sig: &ByteArray) -> Result<(), ScriptError> {
+        if sig.is_empty() || !self.flags.contains(VerifyFlags::STRICTENC) {
+            return Ok(());
+        }
+        let sig_hash_type = sig[sig.len() - 1];
+        let base_type = sig_hash_type & !(SIGHASH_FORKID_BIT | SIGHASH_ANYONECANPAY_BIT);
+        if !(1..=3).contains(&base_type) {
+            return Err(ScriptError::UndefinedSigHashType(sig_hash_type));
+        }
+        let uses_fork_id = sig_hash_type & SIGHASH_FORKID_BIT != 0;
+        let fork_id_enabled = self.flags.contains(VerifyFlags::SIGHASH_FORKID);
+        if uses_fork_id && !fork_id_enabled {
+            return Err(ScriptError::IllegalForkId(sig_hash_type));
+        }
+        if !uses_fork_id && fork_id_enabled {
+            return Err(ScriptError::MustUseForkId(sig_hash_type));
+        }
+        Ok(())
+    }
+
+    /// The scriptCode signatures currently commit to: the current script, starting after the
+    /// last executed OP_CODESEPARATOR.
+    pub fn script_code(&self) -> Result<ByteArray, ScriptError> {
+        serialize_ops(self.script_code_ops().iter().map(|op| &op.op))
+            .map_err(|err| ScriptError::OpcodeMsg(Opcode::OP_CODESEPARATOR, err.to_string().into()))
+    }
+
+    fn script_code_ops(&self) -> &[TaggedOp] {
+        let ops = self.script.ops_arc();
+        &ops[self.code_separator.min(ops.len())..]
+    }
+
+    /// The scriptCode of every signature check executed so far, in execution order.
+    pub fn sig_script_codes(&self) -> &[SigScriptCode] {
+        &self.sig_script_codes
+    }
+
+    fn record_script_code(&mut self) -> Result<(), ScriptError> {
+        let script_code = self.script_code()?;
+        self.sig_script_codes.push(SigScriptCode {
+            instruction_pointer: self.instruction_pointer,
+            code_separator: self.code_separator,
+            script_code,
+        });
+        Ok(())
+    }
+
+    /// Splits off the sighash byte of `sig` and calculates the signed message hash for it,
+    /// using the current scriptCode. Returns the message hash and the signature without the
+    /// sighash byte.
+    fn sig_hash_msg(&self, sig: &ByteArray) -> Result<(ByteArray, ByteArray), ScriptError> {
+        self.require_tx_context()?;
+        let mut sig_ser = sig.to_vec();
+        let sig_hash_flags = if !sig_ser.is_empty() {
+            [SigHashFlags::from_u8(sig_ser.remove(sig_ser.len() - 1))]
+        } else {
+            [SigHashFlags::DEFAULT]
+        };
+        let mut preimage = self.tx.preimages(&sig_hash_flags).swap_remove(self.input_idx).swap_remove(0);
+        preimage.script_code = Script::new(self.script_code_ops().to_vec());
+        let sig_ser = sig.clone().apply_function(sig_ser, Function::ToDataSig);
+        Ok((Sha256d::digest(preimage.ser()).into_byte_array(), sig_ser))
+    }
+
+    fn set_top_sig_scheme(&mut self, sig_scheme: SignatureScheme) {
+        if let Some(top) = self.stack.last_mut() {
+            top.sig_scheme = Some(sig_scheme);
+        }
+    }
+
+    fn verify_ecdsa(
+        &self,
+        opcode: Opcode,
+        pubkey: &ByteArray,
+        msg: &ByteArray,
+        sig_ser: &ByteArray,
+    ) -> Result<bool, ScriptError> {
+        match self.ecc.verify(pubkey, msg, sig_ser) {
+            Ok(validity) => Ok(validity),
+            Err(Error::InvalidPubkey) => Err(ScriptError::InvalidPubKey(pubkey.clone())),
+            Err(Error::InvalidSignatureFormat) => {
+                Err(ScriptError::InvalidSignatureFormat(sig_ser.clone()))
+            }
+            Err(err) => Err(ScriptError::OpcodeMsg(opcode, err.to_string().into())),
+        }
+    }
+
+    /// Legacy (ECDSA) multisig. `pubkeys` and `sigs` are in the order they have been popped
+    /// off the stack, which is the order in which they are matched against each other.
+    fn check_multisig_legacy(
+        &self,
+        opcode: Opcode,
+        pubkeys: &[ByteArray],
+        sigs: &[ByteArray],
+    ) -> Result<bool, ScriptError> {
+        let mut sig_idx = 0;
+        let mut pubkey_idx = 0;
+        let mut success = true;
+        while success && sig_idx < sigs.len() {
+            let sig = &sigs[sig_idx];
+            if sig.len() == SCHNORR_SIG_LEN + 1 {
+                return Err(ScriptError::SchnorrSignatureInLegacyMultisig(sig.clone()));
+            }
+            let pubkey = &pubkeys[pubkey_idx];
+            self.check_sig_encoding(sig, true)?;
+            self.check_pubkey_encoding(pubkey)?;
+            if !sig.is_empty() {
+                let (msg, sig_ser) = self.sig_hash_msg(sig)?;
+                if self.verify_ecdsa(opcode, pubkey, &msg, &sig_ser)? {
+                    sig_idx += 1;
+                }
+            }
+            pubkey_idx += 1;
+            if sigs.len() - sig_idx > pubkeys.len() - pubkey_idx {
+                success = false;
+            }
+        }
+        let null_fail = self.flags.contains(VerifyFlags::NULLFAIL);
+        if !success && null_fail && sigs.iter().any(|sig| !sig.is_empty()) {
+            return Err(ScriptError::MultiSigNullFail);
+        }
+        Ok(success)
+    }
+
+    /// Schnorr multisig, where the dummy element is a bitfield selecting the public keys
+    /// to check. `pubkeys` and `sigs` are in the order they have been popped off the stack.
+    fn check_multisig_schnorr(
+        &self,
+        bitfield: &ByteArray,
+        pubkeys: &[ByteArray],
+        sigs: &[ByteArray],
+    ) -> Result<bool, ScriptError> {
+        let expected_size = pubkeys.len().div_ceil(8);
+        if bitfield.len() != expected_size {
+            return Err(ScriptError::InvalidBitfieldSize {
+                expected: expected_size,
+                actual: bitfield.len(),
+            });
+        }
+        let check_bits = bitfield
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits, (idx, &byte)| bits | (byte as u32) << (8 * idx));
+        if check_bits >> pubkeys.len() != 0 {
+            return Err(ScriptError::InvalidBitRange(pubkeys.len()));
+        }
+        let num_set_bits = check_bits.count_ones() as usize;
+        if num_set_bits != sigs.len() {
+            return Err(ScriptError::InvalidBitCount {
+                expected: sigs.len(),
+                actual: num_set_bits,
+            });
+        }
+        // bit 0 refers to the bottom-most public key, which has been popped last
+        let selected_pubkeys = pubkeys
+            .iter()
+            .rev()
+            .enumerate()
+            .filter(|&(idx, _)| (check_bits >> idx) & 1 == 1)
+            .map(|(_, pubkey)| pubkey);
+        for (sig, pubkey) in sigs.iter().rev().zip(selected_pubkeys) {
+            if sig.len() != SCHNORR_SIG_LEN + 1 {
+                return Err(ScriptError::InvalidSchnorrSignatureLength(sig.clone()));
+            }
+            self.check_sig_hash_type(sig)?;
+            self.check_pubkey_encoding(pubkey)?;
+            let (msg, sig_ser) = self.sig_hash_msg(sig)?;
+            if !verify_schnorr(pubkey, msg.data(), &sig_ser)? {
+                return Err(ScriptError::InvalidSignature(msg, sig_ser));
+            }
+        }
+        Ok(true)
+    }
+
     fn run_opcode(
         &mut self,
         op: &TaggedOp,
@@ -264,14 +1106,21 @@ impl<E: ECC> ScriptInterpreter<E> {
         use Opcode::*;
 
         match opcode {
+            // deserialized scripts contain these as opcodes instead of Op::PushInteger
+            OP_0 => self.push_num(op, 0),
+            OP_1NEGATE => self.push_num(op, -1),
+            OP_1 | OP_2 | OP_3 | OP_4 | OP_5 | OP_6 | OP_7 | OP_8 | OP_9 | OP_10 | OP_11 | OP_12
+            | OP_13 | OP_14 | OP_15 | OP_16 => {
+                self.push_num(op, opcode as i64 - OP_1 as i64 + 1);
+            }
             OP_PICK => {
-                let item_idx = self.pop_depth_to_idx()?;
+                let item_idx = self.pop_depth_to_idx(opcode)?;
                 let mut item = self.stack[item_idx].clone();
                 item.delta = StackItemDelta::Added;
                 self.stack.push(item);
             }
             OP_ROLL => {
-                let item_idx = self.pop_depth_to_idx()?;
+                let item_idx = self.pop_depth_to_idx(opcode)?;
                 self.stack[item_idx..]
                     .iter_mut()
                     .for_each(|item| item.delta = StackItemDelta::MovedIndirectly);
@@ -280,21 +1129,21 @@ impl<E: ECC> ScriptInterpreter<E> {
                 self.stack.push(item);
             }
             OP_TOALTSTACK => {
-                let top = self.stack.remove(self.stack.len() - 1);
+                let top = self.pop(opcode)?;
                 self.alt_stack.push(top);
             }
             OP_FROMALTSTACK => {
-                let top = self.alt_stack.remove(self.alt_stack.len() - 1);
+                let top = self.alt_stack.pop().ok_or(AltStackEmpty(opcode))?;
                 self.push_tagged_data(op, top.data);
             }
             OP_CAT => {
-                let first = self.pop_byte_array()?;
-                let second = self.pop_byte_array()?;
+                let first = self.pop_byte_array(opcode)?;
+                let second = self.pop_byte_array(opcode)?;
                 self.push_tagged_data(op, StackItemData::ByteArray(second.concat(first)));
             }
             OP_SPLIT => {
-                let split_idx = self.pop_int()?.value().try_into()?;
-                let top = self.pop_byte_array()?;
+                let split_idx = self.pop_int(opcode)?.value().try_into()?;
+                let top = self.pop_byte_array(opcode)?;
                 let (left, right) = top
                     .split(split_idx)
                     .map_err(|err| ScriptError::OpcodeMsg(OP_SPLIT, err.to_string().into()))?;
@@ -302,26 +1151,42 @@ impl<E: ECC> ScriptInterpreter<E> {
                 self.push_tagged_data_idx(op, StackItemData::ByteArray(right), 1);
             }
             OP_NUM2BIN => {
-                let n_bytes = self.pop_int()?;
-                let int = self.pop_int()?;
-                self.push_tagged_data(
-                    op,
-                    StackItemData::ByteArray(
-                        ByteArray::from_int(int, n_bytes)
-                            .map_err(|err| ScriptError::OpcodeMsg(OP_NUM2BIN, err.to_string().into()))?,
-                    ),
-                );
+                let size = self.pop_num(opcode)?;
+                if size < 0 || size as usize > MAX_SCRIPT_ELEMENT_SIZE {
+                    return Err(ScriptError::OpcodeMsg(OP_NUM2BIN, format!("Invalid size: {}", size).into()));
+                }
+                let size = size as usize;
+                // like the reference implementation, the number isn't limited to max_num_len
+                let mut num = self.pop_byte_array(opcode)?.to_vec();
+                minimally_encode_script_num(&mut num);
+                if num.len() > size {
+                    return Err(ScriptError::OpcodeMsg(
+                        OP_NUM2BIN,
+                        format!("{} byte number doesn't fit into {} bytes", num.len(), size).into(),
+                    ));
+                }
+                if num.len() < size {
+                    let sign_bit = match num.last_mut() {
+                        Some(last) => {
+                            let sign_bit = *last & 0x80;
+                            *last &= 0x7f;
+                            sign_bit
+                        }
+                        None => 0x00,
+                    };
+                    num.resize(size - 1, 0x00);
+                    num.push(sign_bit);
+                }
+                self.push_tagged_data(op, StackItemData::ByteArray(num.into()));
             }
             OP_BIN2NUM => {
-                let array = self.pop_byte_array()?;
-                self.push_tagged_data(op, StackItemData::Integer(
-                    vec_to_int(&array)
-                        .and_then(|int| Ok(Integer::new(int)?))
-                        .map_err(|err|  ScriptError::OpcodeMsg(OP_BIN2NUM, err.to_string().into()))?.into()
-                ));
+                let mut num = self.pop_byte_array(opcode)?.to_vec();
+                minimally_encode_script_num(&mut num);
+                let value = decode_script_num(&num, self.max_num_len(), false)?;
+                self.push_num(op, value);
             }
             OP_SIZE => {
-                let array = &self.stack[self.stack.len() - 1].data;
+                let array = &self.stack.last().ok_or(StackEmpty(opcode))?.data;
                 if let StackItemData::ByteArray(array) = array {
                     let len = Integer::new(array.len())?;
                     self.push_tagged_data(op, StackItemData::Integer(len));
@@ -330,43 +1195,43 @@ impl<E: ECC> ScriptInterpreter<E> {
                 }
             }
             OP_SHA1 => {
-                let array = self.pop_byte_array()?;
+                let array = self.pop_byte_array(opcode)?;
                 self.push_tagged_data(
                     op,
                     StackItemData::ByteArray(Sha1::digest(array).into_byte_array()),
                 );
             }
             OP_RIPEMD160 => {
-                let array = self.pop_byte_array()?;
+                let array = self.pop_byte_array(opcode)?;
                 self.push_tagged_data(
                     op,
                     StackItemData::ByteArray(Ripemd160::digest(array).into_byte_array()),
                 );
             }
             OP_HASH256 => {
-                let array = self.pop_byte_array()?;
+                let array = self.pop_byte_array(opcode)?;
                 self.push_tagged_data(
                     op,
                     StackItemData::ByteArray(Sha256d::digest(array).into_byte_array()),
                 );
             }
             OP_SHA256 => {
-                let array = self.pop_byte_array()?;
+                let array = self.pop_byte_array(opcode)?;
                 self.push_tagged_data(
                     op,
                     StackItemData::ByteArray(Sha256::digest(array).into_byte_array()),
                 );
             }
             OP_HASH160 => {
-                let array = self.pop_byte_array()?;
+                let array = self.pop_byte_array(opcode)?;
                 self.push_tagged_data(
                     op,
                     StackItemData::ByteArray(Hash160::digest(array).into_byte_array()),
                 );
             }
             OP_EQUAL | OP_EQUALVERIFY => {
-                let first = self.pop_byte_array()?;
-                let second = self.pop_byte_array()?;
+                let first = self.pop_byte_array(opcode)?;
+                let second = self.pop_byte_array(opcode)?;
                 let equal = &first == &second;
                 if opcode == OP_EQUALVERIFY {
                     if !equal {
@@ -377,30 +1242,30 @@ impl<E: ECC> ScriptInterpreter<E> {
                 }
             }
             OP_NUMEQUAL => {
-                let first = self.pop_int()?;
-                let second = self.pop_int()?;
+                let first = self.pop_num(opcode)?;
+                let second = self.pop_num(opcode)?;
                 self.push_tagged_data(op, StackItemData::Boolean(first == second));
             }
             OP_NUMEQUALVERIFY => {
-                let first = self.pop_int()?;
-                let second = self.pop_int()?;
+                let first = self.pop_num(opcode)?;
+                let second = self.pop_num(opcode)?;
                 if first != second {
                     return Err(VerifyFailed);
                 }
             }
             OP_BOOLAND => {
-                let first = self.pop_bool()?;
-                let second = self.pop_bool()?;
+                let first = self.pop_int_bool(opcode)?;
+                let second = self.pop_int_bool(opcode)?;
                 self.push_tagged_data(op, StackItemData::Boolean(first && second));
             }
             OP_BOOLOR => {
-                let first = self.pop_bool()?;
-                let second = self.pop_bool()?;
+                let first = self.pop_int_bool(opcode)?;
+                let second = self.pop_int_bool(opcode)?;
                 self.push_tagged_data(op, StackItemData::Boolean(first || second));
             }
             OP_AND | OP_OR | OP_XOR => {
-                let first = self.pop_byte_array()?;
-                let second = self.pop_byte_array()?;
+                let first = self.pop_byte_array(opcode)?;
+                let second = self.pop_byte_array(opcode)?;
                 if first.len() != second.len() {
                     return Err(ScriptError::OpcodeMsg(
                         OP_AND,
@@ -418,90 +1283,105 @@ impl<E: ECC> ScriptInterpreter<E> {
                 self.push_tagged_data(op, StackItemData::ByteArray(result.into()));
             }
             OP_NOT => {
-                let boolean = self.pop_bool()?;
+                let boolean = self.pop_int_bool(opcode)?;
                 self.push_tagged_data(op, StackItemData::Boolean(!boolean));
             }
             OP_GREATERTHAN => {
-                let first = self.pop_int()?;
-                let second = self.pop_int()?;
+                let first = self.pop_num(opcode)?;
+                let second = self.pop_num(opcode)?;
                 self.push_tagged_data(op, StackItemData::Boolean(second > first));
             }
             OP_GREATERTHANOREQUAL => {
-                let first = self.pop_int()?;
-                let second = self.pop_int()?;
+                let first = self.pop_num(opcode)?;
+                let second = self.pop_num(opcode)?;
                 self.push_tagged_data(op, StackItemData::Boolean(second >= first));
             }
             OP_LESSTHANOREQUAL => {
-                let first = self.pop_int()?;
-                let second = self.pop_int()?;
+                let first = self.pop_num(opcode)?;
+                let second = self.pop_num(opcode)?;
                 self.push_tagged_data(op, StackItemData::Boolean(second <= first));
             }
             OP_LESSTHAN => {
-                let first = self.pop_int()?;
-                let second = self.pop_int()?;
+                let first = self.pop_num(opcode)?;
+                let second = self.pop_num(opcode)?;
                 self.push_tagged_data(op, StackItemData::Boolean(second < first));
             }
             OP_MIN => {
-                let first = self.pop_int()?.value();
-                let second = self.pop_int()?.value();
-                let result = Integer::new(second.min(first))?;
-                self.push_tagged_data(op, StackItemData::Integer(result));
+                let first = self.pop_num(opcode)?;
+                let second = self.pop_num(opcode)?;
+                self.push_num(op, second.min(first));
             }
             OP_MAX => {
-                let first = self.pop_int()?.value();
-                let second = self.pop_int()?.value();
-                let result = Integer::new(second.max(first))?;
-                self.push_tagged_data(op, StackItemData::Integer(result));
+                let first = self.pop_num(opcode)?;
+                let second = self.pop_num(opcode)?;
+                self.push_num(op, second.max(first));
             }
             OP_WITHIN => {
-                let max = self.pop_int()?;
-                let min = self.pop_int()?;
-                let value = self.pop_int()?;
+                let max = self.pop_num(opcode)?;
+                let min = self.pop_num(opcode)?;
+                let value = self.pop_num(opcode)?;
                 self.push_tagged_data(op, StackItemData::Boolean(value >= min && value < max));
             }
             OP_0NOTEQUAL => {
-                let top = self.pop_int()?;
-                self.push_tagged_data(op, StackItemData::Boolean(top != Integer::ZERO));
+                let top = self.pop_num(opcode)?;
+                self.push_tagged_data(op, StackItemData::Boolean(top != 0));
             }
             OP_1ADD => {
-                let a = self.pop_int()?;
-                self.push_tagged_data(op, StackItemData::Integer((a + 1).integer()?));
+                let a = self.pop_num(opcode)?;
+                self.push_num(op, self.checked_num(opcode, a.checked_add(1))?);
             }
             OP_1SUB => {
-                let a = self.pop_int()?;
-                self.push_tagged_data(op, StackItemData::Integer((a - 1).integer()?));
+                let a = self.pop_num(opcode)?;
+                self.push_num(op, self.checked_num(opcode, a.checked_sub(1))?);
             }
             OP_NEGATE => {
-                let a = self.pop_int()?;
-                self.push_tagged_data(op, StackItemData::Integer(-a));
+                let a = self.pop_num(opcode)?;
+                self.push_num(op, -a);
             }
             OP_ABS => {
-                let a = self.pop_int()?;
-                self.push_tagged_data(op, StackItemData::Integer(Integer::new(a.abs())?));
+                let a = self.pop_num(opcode)?;
+                self.push_num(op, a.abs());
             }
             OP_ADD => {
-                let b = self.pop_int()?;
-                let a = self.pop_int()?;
-                self.push_tagged_data(op, StackItemData::Integer((a + b).integer()?));
+                let b = self.pop_num(opcode)?;
+                let a = self.pop_num(opcode)?;
+                self.push_num(op, self.checked_num(opcode, a.checked_add(b))?);
             }
             OP_SUB => {
-                let b = self.pop_int()?;
-                let a = self.pop_int()?;
-                self.push_tagged_data(op, StackItemData::Integer((a - b).integer()?));
+                let b = self.pop_num(opcode)?;
+                let a = self.pop_num(opcode)?;
+                self.push_num(op, self.checked_num(opcode, a.checked_sub(b))?);
+            }
+            OP_MUL => {
+                let b = self.pop_num(opcode)?;
+                let a = self.pop_num(opcode)?;
+                self.push_num(op, self.checked_num(opcode, a.checked_mul(b))?);
             }
             OP_DIV => {
-                let b = self.pop_int()?;
-                let a = self.pop_int()?;
-                self.push_tagged_data(op, StackItemData::Integer((a / b).integer()?));
+                let b = self.pop_num(opcode)?;
+                let a = self.pop_num(opcode)?;
+                if b == 0 {
+                    return Err(DivisionByZero(opcode));
+                }
+                self.push_num(op, a / b);
             }
             OP_MOD => {
-                let b = self.pop_int()?;
-                let a = self.pop_int()?;
-                self.push_tagged_data(op, StackItemData::Integer((a % b).integer()?));
+                let b = self.pop_num(opcode)?;
+                let a = self.pop_num(opcode)?;
+                if b == 0 {
+                    return Err(DivisionByZero(opcode));
+                }
+                self.push_num(op, a % b);
             }
-            OP_IF => {
-                let top = if is_executed { self.pop_bool()? } else { false };
-                self.exec_stack.push(top);
+            OP_IF | OP_NOTIF => {
+                let mut condition = false;
+                if is_executed {
+                    condition = self.pop_condition(opcode)?;
+                    if opcode == OP_NOTIF {
+                        condition = !condition;
+                    }
+                }
+                self.exec_stack.push(condition);
             }
             OP_ELSE => {
                 let top_exec = self
@@ -516,56 +1396,99 @@ impl<E: ECC> ScriptInterpreter<E> {
                     .ok_or(ScriptError::UnbalancedConditionals(OP_ENDIF))?;
             }
             OP_VERIFY => {
-                let top = self.pop()?;
-                if !top.to_bool() {
+                if !self.pop_bool(opcode)? {
                     return Err(VerifyFailed);
                 }
             }
             OP_CHECKSIG | OP_CHECKSIGVERIFY | OP_CHECKDATASIG | OP_CHECKDATASIGVERIFY => {
-                let pubkey = self.pop_byte_array()?;
+                let pubkey = self.pop_byte_array(opcode)?;
                 let (msg, sig_ser) = match opcode {
                     OP_CHECKSIG | OP_CHECKSIGVERIFY => {
-                        let sig = self.pop_byte_array()?;
-                        let mut sig_ser = sig.to_vec();
-                        let sig_hash_flags = if sig_ser.len() > 0 {
-                            [SigHashFlags::from_u8(sig_ser.remove(sig_ser.len() - 1))]
-                        } else {
-                            [SigHashFlags::DEFAULT]
-                        };
-                        let preimages =
-                            self.tx.preimages(&sig_hash_flags)[self.input_idx][0].ser();
-                        let sig = sig.apply_function(sig_ser, Function::ToDataSig);
-                        (Sha256d::digest(preimages).into_byte_array(), sig)
+                        let sig = self.pop_byte_array(opcode)?;
+                        self.check_sig_encoding(&sig, true)?;
+                        self.record_script_code()?;
+                        self.sig_hash_msg(&sig)?
                     }
                     OP_CHECKDATASIG | OP_CHECKDATASIGVERIFY => {
-                        let msg = Sha256::digest(self.pop_byte_array()?).into_byte_array();
-                        (msg, self.pop_byte_array()?)
+                        let msg = Sha256::digest(self.pop_byte_array(opcode)?).into_byte_array();
+                        let sig = self.pop_byte_array(opcode)?;
+                        self.check_sig_encoding(&sig, false)?;
+                        (msg, sig)
                     }
                     _ => unreachable!(),
                 };
-                let validity = match self.ecc.verify(&pubkey, &msg, &sig_ser) {
-                    Ok(validity) => validity,
-                    Err(Error::InvalidPubkey) => {
-                        return Err(ScriptError::InvalidPubKey(pubkey))
+                self.check_pubkey_encoding(&pubkey)?;
+                let sig_scheme = SignatureScheme::of_sig(&sig_ser);
+                let validity = if !sig_ser.is_empty() {
+                    self.add_sig_checks(1)?;
+                    match sig_scheme {
+                        SignatureScheme::Schnorr => verify_schnorr(&pubkey, msg.data(), &sig_ser)?,
+                        SignatureScheme::Ecdsa => self.verify_ecdsa(opcode, &pubkey, &msg, &sig_ser)?,
                     }
-                    Err(Error::InvalidSignatureFormat) => {
-                        return Err(ScriptError::InvalidSignatureFormat(sig_ser))
-                    }
-                    Err(err) => return Err(ScriptError::OpcodeMsg(opcode, err.to_string().into())),
+                } else {
+                    false
                 };
                 if opcode == OP_CHECKSIG || opcode == OP_CHECKDATASIG {
-                    if sig_ser.len() > 0 && !validity {
+                    let null_fail = self.flags.contains(VerifyFlags::NULLFAIL);
+                    if null_fail && !sig_ser.is_empty() && !validity {
                         return Err(InvalidSignature(msg, sig_ser));
                     }
                     self.push_tagged_data(op, StackItemData::Boolean(validity));
+                    if !sig_ser.is_empty() {
+                        self.set_top_sig_scheme(sig_scheme);
+                    }
                 } else {
                     if !validity {
                         return Err(InvalidSignature(msg, sig_ser));
                     }
                 }
             }
+            OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY => {
+                let num_pubkeys = self.pop_int(opcode)?;
+                if num_pubkeys.value() < 0 || num_pubkeys.value() > MAX_PUBKEYS_PER_MULTISIG {
+                    return Err(InvalidPubKeyCount(num_pubkeys));
+                }
+                self.add_op_count(num_pubkeys.value() as usize)?;
+                let pubkeys = (0..num_pubkeys.value())
+                    .map(|_| self.pop_byte_array(opcode))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let num_sigs = self.pop_int(opcode)?;
+                if num_sigs.value() < 0 || num_sigs.value() > num_pubkeys.value() {
+                    return Err(InvalidSignatureCount(num_sigs));
+                }
+                let sigs = (0..num_sigs.value())
+                    .map(|_| self.pop_byte_array(opcode))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let dummy = self.pop_byte_array(opcode)?;
+                self.record_script_code()?;
+                let is_schnorr = !dummy.is_empty() && self.flags.contains(VerifyFlags::SCHNORR_MULTISIG);
+                let success = if is_schnorr {
+                    self.add_sig_checks(sigs.len())?;
+                    self.check_multisig_schnorr(&dummy, &pubkeys, &sigs)?
+                } else {
+                    if !dummy.is_empty() && self.flags.contains(VerifyFlags::NULLDUMMY) {
+                        return Err(NonNullDummy(dummy));
+                    }
+                    if sigs.iter().any(|sig| !sig.is_empty()) {
+                        self.add_sig_checks(pubkeys.len())?;
+                    }
+                    self.check_multisig_legacy(opcode, &pubkeys, &sigs)?
+                };
+                if opcode == OP_CHECKMULTISIG {
+                    self.push_tagged_data(op, StackItemData::Boolean(success));
+                    if sigs.iter().any(|sig| !sig.is_empty()) {
+                        self.set_top_sig_scheme(if is_schnorr {
+                            SignatureScheme::Schnorr
+                        } else {
+                            SignatureScheme::Ecdsa
+                        });
+                    }
+                } else if !success {
+                    return Err(CheckMultiSigVerifyFailed);
+                }
+            }
             OP_REVERSEBYTES => {
-                let array = self.pop_byte_array()?;
+                let array = self.pop_byte_array(opcode)?;
                 let mut reversed = array.to_vec();
                 reversed.reverse();
                 self.push_tagged_data(
@@ -573,19 +1496,39 @@ impl<E: ECC> ScriptInterpreter<E> {
                     StackItemData::ByteArray(array.apply_function(reversed, Function::Reverse)),
                 );
             }
-            OP_CODESEPARATOR => {}
+            OP_CODESEPARATOR => {
+                self.code_separator = self.instruction_pointer + 1;
+            }
+            OP_NOP1 | OP_NOP4 | OP_NOP5 | OP_NOP6 | OP_NOP7 | OP_NOP8 | OP_NOP9 | OP_NOP10 => {
+                self.check_upgradable_nop(opcode)?;
+            }
+            OP_CHECKLOCKTIMEVERIFY if !self.flags.contains(VerifyFlags::CHECKLOCKTIMEVERIFY) => {
+                self.check_upgradable_nop(opcode)?;
+            }
+            OP_CHECKSEQUENCEVERIFY if !self.flags.contains(VerifyFlags::CHECKSEQUENCEVERIFY) => {
+                self.check_upgradable_nop(opcode)?;
+            }
             OP_CHECKLOCKTIMEVERIFY => {
-                let lock_time = self.pop_int()?;
-                if self.tx.lock_time() < lock_time.value().try_into()? {
-                    return Err(VerifyFailed);
+                let required = self.peek_script_num(opcode, 5)?;
+                if required < 0 {
+                    return Err(NegativeLockTime(OP_CHECKLOCKTIMEVERIFY, required));
                 }
-                self.push_tagged_data(op, StackItemData::Integer(lock_time));
+                self.check_lock_time(required)?;
             }
             OP_CHECKSEQUENCEVERIFY => {
-                // TODO
+                let required = self.peek_script_num(opcode, 5)?;
+                if required < 0 {
+                    return Err(NegativeLockTime(OP_CHECKSEQUENCEVERIFY, required));
+                }
+                if required as u32 & SEQUENCE_LOCKTIME_DISABLE_FLAG == 0 {
+                    self.check_sequence(required)?;
+                }
             }
             _ => {
                 let behavior = opcode.behavior();
+                if self.stack.len() < behavior.input_types.len() {
+                    return Err(StackEmpty(opcode));
+                }
                 let input_items = self
                     .stack
                     .drain(self.stack.len() - behavior.input_types.len()..)
@@ -605,3 +1548,16 @@ impl<E: ECC> ScriptInterpreter<E> {
         Ok(())
     }
 }
+
+fn serialize_script(script: &Script) -> Result<ByteArray, ScriptError> {
+    serialize_ops(script.ops_arc().iter().map(|op| &op.op))
+        .map_err(|err| ScriptError::InvalidRedeemScript(err.to_string().into()))
+}
+
+fn is_push_op(op: &Op) -> bool {
+    match *op {
+        Op::Code(opcode) => opcode as u8 <= Opcode::OP_16 as u8,
+        Op::Invalid(_) => false,
+        Op::PushByteArray { .. } | Op::PushBoolean(_) | Op::PushInteger(_) => true,
+    }
+}
@@ -0,0 +1,32 @@
+/// Maximum number of non-push ops per script.
+pub const MAX_OPS_PER_SCRIPT: usize = 201;
+
+/// Maximum number of items on the stack and alt stack combined.
+pub const MAX_STACK_SIZE: usize = 1000;
+
+/// Maximum size of a stack item in bytes.
+pub const MAX_SCRIPT_ELEMENT_SIZE: usize = 520;
+
+/// Maximum size of a script in bytes.
+pub const MAX_SCRIPT_SIZE: usize = 10_000;
+
+/// Maximum number of SigChecks allowed for an input script of the given size.
+pub fn max_input_sig_checks(input_script_size: usize) -> usize {
+    (input_script_size + 60) / 43
+}
+
+/// Resources used by the interpreter so far, to be compared against the consensus limits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResourceUsage {
+    /// Non-push ops of the current script, including unexecuted ones and
+    /// the public keys of OP_CHECKMULTISIG.
+    pub op_count: usize,
+    /// SigChecks of the whole input, i.e. the number of signature verifications.
+    pub sig_checks: usize,
+    /// Largest number of items on the stack and alt stack combined.
+    pub max_stack_depth: usize,
+    /// Largest stack item pushed, in bytes.
+    pub max_element_size: usize,
+    /// Largest script evaluated, in bytes.
+    pub max_script_size: usize,
+}
@@ -0,0 +1,79 @@
+//! Evaluation of script fragments without a real transaction.
+
+use bitcoin_cash::{Hashed, Script, Sha256d, TxInput, TxOutpoint, TxOutput, UnhashedTx, ECC};
+use std::sync::Arc;
+
+use crate::{ConstructionError, ScriptError, ScriptInterpreter, VerifyFlags};
+
+/// Fields of the synthetic tx a script is evaluated against by `ScriptInterpreter::from_scripts`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MockTxContext {
+    pub version: i32,
+    pub lock_time: u32,
+    /// Sequence of the evaluated input.
+    pub sequence: u32,
+    /// Value of the UTXO spent by the evaluated input.
+    pub value: u64,
+}
+
+impl Default for MockTxContext {
+    fn default() -> Self {
+        MockTxContext {
+            version: 2,
+            lock_time: 0,
+            sequence: 0xffff_ffff,
+            value: 0,
+        }
+    }
+}
+
+impl<E: ECC> ScriptInterpreter<E> {
+    /// Interpreter for `input_script` followed by `lock_script`, which is run directly, i.e.
+    /// not as P2SH. The scripts are put into a tx with a single input and a single empty output.
+    /// Without `tx_context`, ops which depend on the tx, like OP_CHECKSIG, fail with
+    /// `ScriptError::NoTxContext`.
+    pub fn from_scripts(
+        input_script: Script,
+        lock_script: Script,
+        tx_context: Option<MockTxContext>,
+        ecc: Arc<E>,
+        flags: VerifyFlags,
+    ) -> Result<Self, ConstructionError> {
+        let context = tx_context.unwrap_or_default();
+        let tx = UnhashedTx {
+            version: context.version,
+            inputs: vec![TxInput {
+                prev_out: TxOutpoint {
+                    tx_hash: Sha256d::from_slice_le(&[0; 32]).expect("32 bytes are a valid hash"),
+                    vout: 0,
+                },
+                script: input_script,
+                sequence: context.sequence,
+                lock_script: Some(lock_script),
+                value: Some(context.value),
+                is_p2sh: Some(false),
+            }],
+            outputs: vec![TxOutput {
+                value: 0,
+                script: Script::default(),
+            }],
+            lock_time: context.lock_time,
+        };
+        let mut interpreter = ScriptInterpreter::new(Arc::new(tx.hashed()), 0, ecc, flags)?;
+        interpreter.has_tx_context = tx_context.is_some();
+        Ok(interpreter)
+    }
+
+    /// Whether the interpreter runs against a tx, which is only missing for scripts evaluated
+    /// with `from_scripts` without a `MockTxContext`.
+    pub fn has_tx_context(&self) -> bool {
+        self.has_tx_context
+    }
+
+    pub(crate) fn require_tx_context(&self) -> Result<(), ScriptError> {
+        if !self.has_tx_context {
+            return Err(ScriptError::NoTxContext);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+use bitcoin_cash::{Integer, StackItemData};
+
+use crate::ScriptError;
+
+/// Decodes a little-endian sign-magnitude script number of at most `max_len` bytes.
+/// If `require_minimal` is set, the encoding has to be minimal.
+pub fn decode_script_num(
+    data: &[u8],
+    max_len: usize,
+    require_minimal: bool,
+) -> Result<i64, ScriptError> {
+    if data.len() > max_len {
+        return Err(ScriptError::ScriptNumOverflow(data.len(), max_len));
+    }
+    let last = match data.last() {
+        Some(&last) => last,
+        None => return Ok(0),
+    };
+    // the most significant byte may only be 0x00 or 0x80 if the next byte's sign bit is set
+    if require_minimal
+        && last & 0x7f == 0
+        && (data.len() <= 1 || data[data.len() - 2] & 0x80 == 0)
+    {
+        return Err(ScriptError::NonMinimalScriptNum(data.to_vec().into()));
+    }
+    let mut value = 0i64;
+    for (idx, &byte) in data.iter().enumerate() {
+        value |= (byte as i64) << (8 * idx);
+    }
+    let sign_bit = 0x80i64 << (8 * (data.len() - 1));
+    if value & sign_bit != 0 {
+        Ok(-(value & !sign_bit))
+    } else {
+        Ok(value)
+    }
+}
+
+/// Interprets a byte array as boolean, like CastToBool of the reference implementation.
+/// Any encoding of zero is false, including negative zero (e.g. 0x80 or 0x0080).
+pub fn cast_to_bool(data: &[u8]) -> bool {
+    data.iter().enumerate().any(|(idx, &byte)| {
+        let is_sign_byte = idx == data.len() - 1 && byte == 0x80;
+        byte != 0 && !is_sign_byte
+    })
+}
+
+/// Encodes a number as minimal little-endian sign-magnitude script number.
+pub fn encode_script_num(value: i64) -> Vec<u8> {
+    if value == 0 {
+        return Vec::new();
+    }
+    let is_negative = value < 0;
+    let mut abs_value = value.unsigned_abs();
+    let mut data = Vec::new();
+    while abs_value > 0 {
+        data.push((abs_value & 0xff) as u8);
+        abs_value >>= 8;
+    }
+    // the sign bit is in the most significant byte, so add a byte if it is already in use
+    if data[data.len() - 1] & 0x80 != 0 {
+        data.push(if is_negative { 0x80 } else { 0x00 });
+    } else if is_negative {
+        let last_idx = data.len() - 1;
+        data[last_idx] |= 0x80;
+    }
+    data
+}
+
+/// Strips superfluous trailing zero bytes of a script number, keeping its sign, like
+/// MinimallyEncode of the reference implementation. Negative zero becomes empty.
+pub fn minimally_encode_script_num(data: &mut Vec<u8>) {
+    let last = match data.last() {
+        Some(&last) => last,
+        None => return,
+    };
+    if last & 0x7f != 0 {
+        return;
+    }
+    if data.len() > 1 && data[data.len() - 2] & 0x80 != 0 {
+        return;
+    }
+    // find the most significant non-zero byte and move the sign bit into it
+    match data[..data.len() - 1].iter().rposition(|&byte| byte != 0) {
+        Some(idx) if data[idx] & 0x80 != 0 => {
+            data[idx + 1] = last;
+            data.truncate(idx + 2);
+        }
+        Some(idx) => {
+            data[idx] |= last;
+            data.truncate(idx + 1);
+        }
+        None => data.clear(),
+    }
+}
+
+/// Stack data of a number, as integer if it fits into 32 bits and as script number otherwise.
+pub(crate) fn script_num_data(value: i64) -> StackItemData {
+    match Integer::new(value) {
+        Ok(int) => StackItemData::Integer(int),
+        Err(_) => StackItemData::ByteArray(encode_script_num(value).into()),
+    }
+}
@@ -0,0 +1,43 @@
+//! Hashes of the P2SH outputs spent by a tx, which the tx itself doesn't contain.
+
+use bitcoin_cash::{ByteArray, Hash160, Hashed, Op, Opcode, Script, ECC};
+
+use crate::{serialize_script, ScriptError, ScriptInterpreter};
+
+/// Script hashes of the P2SH outputs spent by each input of a tx. Inputs only carry the
+/// redeem script, which is checked against the hash of the spent output. Without a known
+/// hash, the hash of the input's redeem script is used instead.
+#[derive(Clone, Debug, Default)]
+pub struct P2shHashes {
+    pub inputs: Vec<Option<ByteArray>>,
+}
+
+impl<E: ECC> ScriptInterpreter<E> {
+    /// Replaces the script hashes of the spent P2SH outputs, which are otherwise derived
+    /// from the redeem scripts of the tx's inputs.
+    pub fn with_p2sh_hashes(mut self, p2sh_hashes: P2shHashes) -> Self {
+        self.p2sh_hashes = p2sh_hashes;
+        self
+    }
+
+    /// Script hash of the P2SH output spent by input `input_idx`.
+    pub(crate) fn p2sh_hash(&self, input_idx: usize) -> Result<ByteArray, ScriptError> {
+        if let Some(Some(script_hash)) = self.p2sh_hashes.inputs.get(input_idx) {
+            return Ok(script_hash.clone());
+        }
+        let redeem_script = self.tx.inputs()[input_idx]
+            .lock_script
+            .as_ref()
+            .ok_or(ScriptError::MissingUtxo(input_idx))?;
+        Ok(Hash160::digest(serialize_script(redeem_script)?).into_byte_array())
+    }
+}
+
+/// Builds the P2SH output script `OP_HASH160 <script_hash> OP_EQUAL`.
+pub(crate) fn p2sh_output_script(script_hash: ByteArray) -> Script {
+    Script::from_ops(vec![
+        Op::Code(Opcode::OP_HASH160),
+        Op::PushByteArray { array: script_hash, is_minimal: true },
+        Op::Code(Opcode::OP_EQUAL),
+    ])
+}
@@ -0,0 +1,193 @@
+use bitcoin_cash::{ByteArray, Hashed, Sha256};
+use secp256k1::{
+    curve::{Field, Jacobian, Scalar, ECMULT_CONTEXT},
+    PublicKey,
+};
+use std::convert::TryInto;
+
+use crate::ScriptError;
+
+/// Length of a BCH Schnorr signature, without the sighash byte.
+pub const SCHNORR_SIG_LEN: usize = 64;
+
+/// Verifies a BCH Schnorr signature (r || s) of the 32 byte `msg_hash` for `pubkey`.
+///
+/// Follows the May 2019 specification: e = SHA256(r || compressed(P) || m),
+/// R = sG - eP must not be infinity, must have a quadratic residue y and x(R) = r.
+pub fn verify_schnorr(pubkey: &ByteArray, msg_hash: &[u8], sig: &ByteArray) -> Result<bool, ScriptError> {
+    if sig.len() != SCHNORR_SIG_LEN {
+        return Err(ScriptError::InvalidSignatureFormat(sig.clone()));
+    }
+    let pubkey_parsed = PublicKey::parse_slice(pubkey.data(), None)
+        .map_err(|_| ScriptError::InvalidPubKey(pubkey.clone()))?;
+    let r_bytes: [u8; 32] = sig.data()[..32].try_into().unwrap();
+    let s_bytes: [u8; 32] = sig.data()[32..].try_into().unwrap();
+
+    let mut r = Field::default();
+    if !r.set_b32(&r_bytes) {
+        return Ok(false);
+    }
+    let mut s = Scalar::default();
+    let s_overflow: bool = s.set_b32(&s_bytes).into();
+    if s_overflow {
+        return Ok(false);
+    }
+
+    let mut challenge = Vec::with_capacity(32 + 33 + msg_hash.len());
+    challenge.extend_from_slice(&r_bytes);
+    challenge.extend_from_slice(&pubkey_parsed.serialize_compressed());
+    challenge.extend_from_slice(msg_hash);
+    let challenge_hash = Sha256::digest(challenge).into_byte_array();
+    let challenge_bytes: [u8; 32] = challenge_hash[..].try_into().unwrap();
+    let mut e = Scalar::default();
+    let _: bool = e.set_b32(&challenge_bytes).into();
+
+    let mut pubkey_jacobian = Jacobian::default();
+    pubkey_jacobian.set_ge(&pubkey_parsed.into());
+    let mut big_r = Jacobian::default();
+    ECMULT_CONTEXT.ecmult(&mut big_r, &pubkey_jacobian, &-e, &s);
+
+    if big_r.is_infinity() || !big_r.has_quad_y_var() {
+        return Ok(false);
+    }
+    Ok(big_r.eq_x_var(&r))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks a test vector of the May 2019 Schnorr specification.
+    fn verify(pubkey: &str, msg_hash: &str, sig: &str) -> Result<bool, ScriptError> {
+        let pubkey = ByteArray::new_unnamed(hex::decode(pubkey).unwrap());
+        let msg_hash = hex::decode(msg_hash).unwrap();
+        let sig = ByteArray::new_unnamed(hex::decode(sig).unwrap());
+        verify_schnorr(&pubkey, &msg_hash, &sig)
+    }
+
+    const PUBKEY_1: &str = "0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798";
+    const PUBKEY_2: &str = "02DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659";
+    const MSG_2: &str = "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89";
+
+    #[test]
+    fn test_valid_signatures() {
+        assert!(verify(
+            PUBKEY_1,
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "787A848E71043D280C50470E8E1532B2DD5D20EE912A45DBDD2BD1DFBF187EF6\
+             7031A98831859DC34DFFEEDDA86831842CCD0079E1F92AF177F7F22CC1DCED05",
+        )
+        .unwrap());
+        assert!(verify(
+            PUBKEY_2,
+            MSG_2,
+            "2A298DACAE57395A15D0795DDBFD1DCB564DA82B0F269BC70A74F8220429BA1D\
+             1E51A22CCEC35599B8F266912281F8365FFC2D035A230434A1A64DC59F7013FD",
+        )
+        .unwrap());
+        assert!(verify(
+            "03FAC2114C2FBB091527EB7C64ECB11F8021CB45E8E7809D3C0938E4B8C0E5F84B",
+            "5E2D58D8B3BCDF1ABADEC7829054F90DDA9805AAB56C77333024B9D0A508B75C",
+            "00DA9B08172A9B6F0466A2DEFD817F2D7AB437E0D253CB5395A963866B3574BE\
+             00880371D01766935B92D2AB4CD5C8A2A5837EC57FED7660773A05F0DE142380",
+        )
+        .unwrap());
+        // fails if the jacobi symbol of x(R) is checked instead of the one of y(R)
+        assert!(verify(
+            "03DEFDEA4CDB677750A420FEE807EACF21EB9898AE79B9768766E4FAA04A2D4A34",
+            "4DF3C3F68FCC83B27E9D42C90431A72499F17875C81A599B566C9889B9696703",
+            "00000000000000000000003B78CE563F89A0ED9414F5AA28AD0D96D6795F9C63\
+             02A8DC32E64E86A333F20EF56EAC9BA30B7246D6D25E22ADB8C6BE1AEB08D49D",
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_pubkey_not_on_curve() {
+        assert!(verify(
+            "03EEFDEA4CDB677750A420FEE807EACF21EB9898AE79B9768766E4FAA04A2D4A34",
+            "4DF3C3F68FCC83B27E9D42C90431A72499F17875C81A599B566C9889B9696703",
+            "00000000000000000000003B78CE563F89A0ED9414F5AA28AD0D96D6795F9C63\
+             02A8DC32E64E86A333F20EF56EAC9BA30B7246D6D25E22ADB8C6BE1AEB08D49D",
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_non_quadratic_residue_r() {
+        assert!(!verify(
+            PUBKEY_2,
+            MSG_2,
+            "2A298DACAE57395A15D0795DDBFD1DCB564DA82B0F269BC70A74F8220429BA1D\
+             FA16AEE06609280A19B67A24E1977E4697712B5FD2943914ECD5F730901B4AB7",
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_negated_pubkey() {
+        assert!(!verify(
+            "03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659",
+            MSG_2,
+            "2A298DACAE57395A15D0795DDBFD1DCB564DA82B0F269BC70A74F8220429BA1D\
+             1E51A22CCEC35599B8F266912281F8365FFC2D035A230434A1A64DC59F7013FD",
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_r_at_infinity() {
+        assert!(!verify(
+            PUBKEY_2,
+            MSG_2,
+            "0000000000000000000000000000000000000000000000000000000000000000\
+             9E9D01AF988B5CEDCE47221BFA9B222721F3FA408915444A4B489021DB55775F",
+        )
+        .unwrap());
+        assert!(!verify(
+            PUBKEY_2,
+            MSG_2,
+            "0000000000000000000000000000000000000000000000000000000000000001\
+             D37DDF0254351836D84B1BD6A795FD5D523048F298C4214D187FE4892947F728",
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_r_mismatch() {
+        assert!(!verify(
+            PUBKEY_2,
+            MSG_2,
+            "4A298DACAE57395A15D0795DDBFD1DCB564DA82B0F269BC70A74F8220429BA1D\
+             1E51A22CCEC35599B8F266912281F8365FFC2D035A230434A1A64DC59F7013FD",
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_r_not_below_field_size() {
+        assert!(!verify(
+            PUBKEY_2,
+            MSG_2,
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F\
+             1E51A22CCEC35599B8F266912281F8365FFC2D035A230434A1A64DC59F7013FD",
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_s_not_below_curve_order() {
+        assert!(!verify(
+            PUBKEY_2,
+            MSG_2,
+            "2A298DACAE57395A15D0795DDBFD1DCB564DA82B0F269BC70A74F8220429BA1D\
+             FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141",
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_invalid_length() {
+        assert!(verify(PUBKEY_1, MSG_2, "00").is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+
+use crate::SCHNORR_SIG_LEN;
+
+/// Signature scheme of a signature checked by the interpreter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SignatureScheme {
+    Ecdsa,
+    Schnorr,
+}
+
+impl SignatureScheme {
+    /// Determines the scheme of a signature without sighash byte. Schnorr signatures are
+    /// exactly 64 bytes long, which is never the case for DER encoded ECDSA signatures.
+    pub fn of_sig(sig: &[u8]) -> Self {
+        if sig.len() == SCHNORR_SIG_LEN {
+            SignatureScheme::Schnorr
+        } else {
+            SignatureScheme::Ecdsa
+        }
+    }
+}
+
+/// Half of the secp256k1 group order, the upper bound of low S values.
+const HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+/// Strict DER check of BIP66 for a signature without sighash byte.
+pub fn is_valid_der_encoding(sig: &[u8]) -> bool {
+    if sig.len() < 8 || sig.len() > 72 {
+        return false;
+    }
+    if sig[0] != 0x30 || sig[1] as usize != sig.len() - 2 {
+        return false;
+    }
+    let len_r = sig[3] as usize;
+    if 5 + len_r >= sig.len() {
+        return false;
+    }
+    let len_s = sig[5 + len_r] as usize;
+    if len_r + len_s + 6 != sig.len() {
+        return false;
+    }
+    if sig[2] != 0x02 || len_r == 0 || sig[4] & 0x80 != 0 {
+        return false;
+    }
+    if len_r > 1 && sig[4] == 0x00 && sig[5] & 0x80 == 0 {
+        return false;
+    }
+    if sig[len_r + 4] != 0x02 || len_s == 0 || sig[len_r + 6] & 0x80 != 0 {
+        return false;
+    }
+    if len_s > 1 && sig[len_r + 6] == 0x00 && sig[len_r + 7] & 0x80 == 0 {
+        return false;
+    }
+    true
+}
+
+/// Checks whether the S value of a strictly DER encoded signature is at most half the order.
+pub fn is_low_der_s(sig: &[u8]) -> bool {
+    let len_r = sig[3] as usize;
+    let len_s = sig[5 + len_r] as usize;
+    let s = &sig[6 + len_r..6 + len_r + len_s];
+    let first_nonzero = s.iter().position(|&byte| byte != 0).unwrap_or(s.len());
+    let s = &s[first_nonzero..];
+    if s.len() != HALF_ORDER.len() {
+        return s.len() < HALF_ORDER.len();
+    }
+    s <= &HALF_ORDER[..]
+}
+
+/// Checks whether the public key is either compressed or uncompressed.
+pub fn is_valid_pubkey_encoding(pubkey: &[u8]) -> bool {
+    match pubkey.first() {
+        Some(0x02) | Some(0x03) => pubkey.len() == 33,
+        Some(0x04) => pubkey.len() == 65,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// DER encoding of a signature with the given R and S values.
+    fn der_sig(r: &[u8], s: &[u8]) -> Vec<u8> {
+        let mut sig = vec![0x30, (r.len() + s.len() + 4) as u8, 0x02, r.len() as u8];
+        sig.extend_from_slice(r);
+        sig.extend_from_slice(&[0x02, s.len() as u8]);
+        sig.extend_from_slice(s);
+        sig
+    }
+
+    #[test]
+    fn test_valid_der() {
+        assert!(is_valid_der_encoding(&der_sig(&[0x01], &[0x01])));
+        assert!(is_valid_der_encoding(&der_sig(&[0x00, 0x81], &[0x00, 0x81])));
+        assert!(is_valid_der_encoding(&der_sig(&[0x7f; 32], &HALF_ORDER)));
+        assert!(is_valid_der_encoding(&der_sig(&[0x7f; 33], &[0x7f; 33])));
+    }
+
+    #[test]
+    fn test_invalid_der_size() {
+        assert!(!is_valid_der_encoding(&[0x30, 0x05, 0x02, 0x01, 0x01, 0x02, 0x00]));
+        assert!(!is_valid_der_encoding(&der_sig(&[0x7f; 33], &[0x7f; 34])));
+    }
+
+    #[test]
+    fn test_invalid_der_structure() {
+        let mut sig = der_sig(&[0x01], &[0x01]);
+        sig[0] = 0x31;
+        assert!(!is_valid_der_encoding(&sig));
+
+        let mut sig = der_sig(&[0x01], &[0x01]);
+        sig[1] = 0x07;
+        assert!(!is_valid_der_encoding(&sig));
+
+        let mut sig = der_sig(&[0x01], &[0x01]);
+        sig[3] = 0x04;
+        assert!(!is_valid_der_encoding(&sig));
+
+        let mut sig = der_sig(&[0x01], &[0x01]);
+        sig.push(0x01);
+        sig[1] += 1;
+        assert!(!is_valid_der_encoding(&sig));
+    }
+
+    #[test]
+    fn test_invalid_der_r() {
+        let mut sig = der_sig(&[0x01], &[0x01]);
+        sig[2] = 0x03;
+        assert!(!is_valid_der_encoding(&sig));
+        assert!(!is_valid_der_encoding(&der_sig(&[], &[0x01, 0x01])));
+        assert!(!is_valid_der_encoding(&der_sig(&[0x81], &[0x01])));
+        assert!(!is_valid_der_encoding(&der_sig(&[0x00, 0x01], &[0x01])));
+    }
+
+    #[test]
+    fn test_invalid_der_s() {
+        let mut sig = der_sig(&[0x01], &[0x01]);
+        sig[5] = 0x03;
+        assert!(!is_valid_der_encoding(&sig));
+        assert!(!is_valid_der_encoding(&der_sig(&[0x01, 0x01], &[])));
+        assert!(!is_valid_der_encoding(&der_sig(&[0x01], &[0x81])));
+        assert!(!is_valid_der_encoding(&der_sig(&[0x01], &[0x00, 0x01])));
+    }
+
+    #[test]
+    fn test_low_s() {
+        assert!(is_low_der_s(&der_sig(&[0x01], &[0x01])));
+        assert!(is_low_der_s(&der_sig(&[0x01], &HALF_ORDER)));
+        let mut above_half_order = HALF_ORDER;
+        above_half_order[31] += 1;
+        assert!(!is_low_der_s(&der_sig(&[0x01], &above_half_order)));
+        let mut order_minus_one = vec![0x00];
+        order_minus_one.extend_from_slice(&hex::decode(
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364140",
+        ).unwrap());
+        assert!(!is_low_der_s(&der_sig(&[0x01], &order_minus_one)));
+    }
+}
@@ -0,0 +1,46 @@
+use super::{run, script_error};
+use crate::{ScriptError, VerificationFailure, VerifyFlags};
+
+#[test]
+fn test_negative_zero_is_false() {
+    for zero in &["<0x80>", "<0x0080>", "<0x000000>"] {
+        let result = run(zero, "OP_VERIFY OP_1", VerifyFlags::STANDARD);
+        assert!(matches!(script_error(result), ScriptError::VerifyFailed));
+        let result = run(zero, "", VerifyFlags::STANDARD);
+        assert!(matches!(
+            result.failure,
+            Some(VerificationFailure::TopOfStackFalse)
+        ));
+    }
+    let result = run("<0x0001>", "OP_VERIFY OP_1", VerifyFlags::STANDARD);
+    assert!(result.success, "{:?}", result.failure);
+}
+
+#[test]
+fn test_boolean_ops_on_byte_arrays() {
+    let result = run("", "<0x01> <0x02> OP_BOOLAND", VerifyFlags::STANDARD);
+    assert!(result.success, "{:?}", result.failure);
+    let result = run(
+        "",
+        "<0x00> <0x02> OP_BOOLOR",
+        VerifyFlags::CONSENSUS - VerifyFlags::MINIMALDATA,
+    );
+    assert!(result.success, "{:?}", result.failure);
+    // negative zero is a non-minimal number, but false otherwise
+    let result = run("", "<0x80> OP_NOT", VerifyFlags::STANDARD);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::NonMinimalScriptNum(_)
+    ));
+    let result = run("", "<0x80> OP_NOT", VerifyFlags::NONE);
+    assert!(result.success, "{:?}", result.failure);
+}
+
+#[test]
+fn test_boolean_ops_number_size() {
+    let result = run("", "<0x0000000001> OP_NOT", VerifyFlags::NONE);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::ScriptNumOverflow(5, 4)
+    ));
+}
@@ -0,0 +1,71 @@
+use super::{
+    ecdsa_sig_asm, pubkey_asm, run, schnorr_sig_asm, schnorr_sign, script_error, sha256,
+    SECRET_KEYS,
+};
+use crate::{ScriptError, VerifyFlags};
+
+fn lock_asm(key_idx: usize) -> String {
+    format!("{} OP_CHECKSIG", pubkey_asm(&SECRET_KEYS[key_idx]))
+}
+
+#[test]
+fn test_checksig_ecdsa() {
+    let input_asm = ecdsa_sig_asm(&SECRET_KEYS[0], &lock_asm(0));
+    let result = run(&input_asm, &lock_asm(0), VerifyFlags::STANDARD);
+    assert!(result.success, "{:?}", result.failure);
+}
+
+#[test]
+fn test_checksig_schnorr() {
+    let input_asm = schnorr_sig_asm(&SECRET_KEYS[0], &lock_asm(0));
+    let result = run(&input_asm, &lock_asm(0), VerifyFlags::STANDARD);
+    assert!(result.success, "{:?}", result.failure);
+    // signed for a different scriptCode
+    let input_asm = schnorr_sig_asm(&SECRET_KEYS[0], &lock_asm(1));
+    let result = run(&input_asm, &lock_asm(0), VerifyFlags::STANDARD);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::InvalidSignature(_, _)
+    ));
+}
+
+#[test]
+fn test_checksig_schnorr_wrong_key() {
+    let lock_asm = format!("{} OP_NOT", lock_asm(1));
+    let input_asm = schnorr_sig_asm(&SECRET_KEYS[0], &lock_asm);
+    let result = run(&input_asm, &lock_asm, VerifyFlags::STANDARD);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::InvalidSignature(_, _)
+    ));
+    let result = run(
+        &input_asm,
+        &lock_asm,
+        VerifyFlags::STANDARD - VerifyFlags::NULLFAIL,
+    );
+    assert!(result.success, "{:?}", result.failure);
+    // an empty signature is allowed to fail
+    let result = run("OP_0", &lock_asm, VerifyFlags::STANDARD);
+    assert!(result.success, "{:?}", result.failure);
+}
+
+#[test]
+fn test_checkdatasig_schnorr() {
+    let data = b"iguana";
+    let lock_asm = format!(
+        "<0x{}> {} OP_CHECKDATASIG",
+        hex::encode(data),
+        pubkey_asm(&SECRET_KEYS[0]),
+    );
+    let sig = schnorr_sign(&SECRET_KEYS[0], &sha256(data));
+    let input_asm = format!("<0x{}>", hex::encode(&sig));
+    let result = run(&input_asm, &lock_asm, VerifyFlags::STANDARD);
+    assert!(result.success, "{:?}", result.failure);
+    let sig = schnorr_sign(&SECRET_KEYS[1], &sha256(data));
+    let input_asm = format!("<0x{}>", hex::encode(&sig));
+    let result = run(&input_asm, &lock_asm, VerifyFlags::STANDARD);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::InvalidSignature(_, _)
+    ));
+}
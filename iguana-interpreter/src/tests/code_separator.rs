@@ -0,0 +1,41 @@
+use super::{ecdsa_sig_asm, interpreter, pubkey_asm, run, script, script_error, SECRET_KEYS};
+use crate::{serialize_script, ScriptError, VerifyFlags};
+
+#[test]
+fn test_script_code_after_code_separator() {
+    let script_code_asm = format!("{} OP_CHECKSIG", pubkey_asm(&SECRET_KEYS[0]));
+    let lock_asm = format!("OP_1 OP_DROP OP_CODESEPARATOR {}", script_code_asm);
+    let input_asm = ecdsa_sig_asm(&SECRET_KEYS[0], &script_code_asm);
+    let mut interpreter = interpreter(&input_asm, &lock_asm, VerifyFlags::STANDARD);
+    let result = interpreter.run();
+    assert!(result.success, "{:?}", result.failure);
+    let sig_script_codes = interpreter.sig_script_codes();
+    assert_eq!(sig_script_codes.len(), 1);
+    assert_eq!(sig_script_codes[0].code_separator, 3);
+    assert_eq!(sig_script_codes[0].instruction_pointer, 4);
+    assert_eq!(
+        sig_script_codes[0].script_code.to_vec(),
+        serialize_script(&script(&script_code_asm))
+            .unwrap()
+            .to_vec(),
+    );
+
+    // signing the whole lock script isn't valid
+    let input_asm = ecdsa_sig_asm(&SECRET_KEYS[0], &lock_asm);
+    let result = run(&input_asm, &lock_asm, VerifyFlags::STANDARD);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::InvalidSignature(_, _)
+    ));
+}
+
+#[test]
+fn test_unexecuted_code_separator() {
+    let lock_asm = format!(
+        "OP_0 OP_IF OP_CODESEPARATOR OP_ENDIF {} OP_CHECKSIG",
+        pubkey_asm(&SECRET_KEYS[0]),
+    );
+    let input_asm = ecdsa_sig_asm(&SECRET_KEYS[0], &lock_asm);
+    let result = run(&input_asm, &lock_asm, VerifyFlags::STANDARD);
+    assert!(result.success, "{:?}", result.failure);
+}
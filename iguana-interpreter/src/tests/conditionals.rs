@@ -0,0 +1,78 @@
+use bitcoin_cash::Opcode::*;
+
+use super::{run, script_error};
+use crate::{ScriptError, VerificationFailure, VerifyFlags};
+
+#[test]
+fn test_notif() {
+    let lock_asm = "OP_NOTIF OP_2 OP_ELSE OP_3 OP_ENDIF OP_3 OP_NUMEQUAL";
+    let result = run("OP_1", lock_asm, VerifyFlags::STANDARD);
+    assert!(result.success, "{:?}", result.failure);
+    let result = run("OP_0", lock_asm, VerifyFlags::STANDARD);
+    assert!(matches!(
+        result.failure,
+        Some(VerificationFailure::TopOfStackFalse)
+    ));
+}
+
+#[test]
+fn test_nested_conditionals() {
+    let lock_asm = "OP_IF OP_NOTIF OP_0 OP_ELSE OP_1 OP_ENDIF OP_ELSE OP_0 OP_ENDIF";
+    let result = run("OP_1 OP_1", lock_asm, VerifyFlags::STANDARD);
+    assert!(result.success, "{:?}", result.failure);
+    let result = run("OP_0 OP_1", lock_asm, VerifyFlags::STANDARD);
+    assert!(matches!(
+        result.failure,
+        Some(VerificationFailure::TopOfStackFalse)
+    ));
+}
+
+#[test]
+fn test_minimal_if() {
+    let lock_asm = "OP_IF OP_1 OP_ELSE OP_0 OP_ENDIF";
+    let result = run("OP_2", lock_asm, VerifyFlags::STANDARD);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::MinimalIf(OP_IF, _)
+    ));
+    let result = run("OP_2", lock_asm, VerifyFlags::CONSENSUS);
+    assert!(result.success, "{:?}", result.failure);
+    // only the empty array is a minimal false
+    let lock_asm = "OP_NOTIF OP_1 OP_ELSE OP_0 OP_ENDIF";
+    let result = run("<0x00>", lock_asm, VerifyFlags::STANDARD);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::MinimalIf(OP_NOTIF, _)
+    ));
+    let result = run("<0x00>", lock_asm, VerifyFlags::CONSENSUS);
+    assert!(result.success, "{:?}", result.failure);
+}
+
+#[test]
+fn test_unterminated_if() {
+    let result = run("OP_1", "OP_IF OP_1 OP_IF OP_1", VerifyFlags::STANDARD);
+    assert!(matches!(
+        result.failure,
+        Some(VerificationFailure::UnterminatedIf(2))
+    ));
+    // branches can't span the input and the lock script
+    let result = run("OP_1 OP_IF", "OP_1 OP_ENDIF", VerifyFlags::NONE);
+    assert!(matches!(
+        result.failure,
+        Some(VerificationFailure::UnterminatedIf(1))
+    ));
+}
+
+#[test]
+fn test_unbalanced_conditionals() {
+    let result = run("OP_1", "OP_ENDIF", VerifyFlags::STANDARD);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::UnbalancedConditionals(OP_ENDIF)
+    ));
+    let result = run("OP_1", "OP_ELSE OP_1", VerifyFlags::STANDARD);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::UnbalancedConditionals(OP_ELSE)
+    ));
+}
@@ -0,0 +1,94 @@
+use super::{ecdsa_sig_asm, pubkey_asm, run, script_error, SECRET_KEYS};
+use crate::{ScriptError, VerificationFailure, VerifyFlags};
+
+#[test]
+fn test_standard_and_consensus() {
+    let result = run("OP_1", "OP_NOP1", VerifyFlags::STANDARD);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::DiscourageUpgradableNops(_)
+    ));
+    let result = run("OP_1", "OP_NOP1", VerifyFlags::CONSENSUS);
+    assert!(result.success, "{:?}", result.failure);
+}
+
+#[test]
+fn test_sig_push_only() {
+    let result = run("OP_1 OP_DUP", "OP_EQUAL", VerifyFlags::CONSENSUS);
+    assert!(matches!(script_error(result), ScriptError::SigPushOnly(_)));
+    let result = run(
+        "OP_1 OP_DUP",
+        "OP_EQUAL",
+        VerifyFlags::CONSENSUS - VerifyFlags::SIGPUSHONLY,
+    );
+    assert!(result.success, "{:?}", result.failure);
+}
+
+#[test]
+fn test_clean_stack() {
+    let result = run("OP_1 OP_1", "", VerifyFlags::CONSENSUS);
+    assert!(matches!(
+        result.failure,
+        Some(VerificationFailure::StackNotClean(2))
+    ));
+    let result = run("OP_1 OP_1", "", VerifyFlags::NONE);
+    assert!(result.success, "{:?}", result.failure);
+}
+
+#[test]
+fn test_minimal_data() {
+    // 0x0100 is a non-minimal encoding of the number 1
+    let lock_asm = "<0x0100> OP_1ADD OP_2 OP_NUMEQUAL";
+    let result = run("", lock_asm, VerifyFlags::CONSENSUS);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::NonMinimalScriptNum(_)
+    ));
+    let result = run(
+        "",
+        lock_asm,
+        VerifyFlags::CONSENSUS - VerifyFlags::MINIMALDATA,
+    );
+    assert!(result.success, "{:?}", result.failure);
+}
+
+#[test]
+fn test_null_fail() {
+    let lock_asm = format!("{} OP_CHECKSIG OP_NOT", pubkey_asm(&SECRET_KEYS[1]));
+    let input_asm = ecdsa_sig_asm(&SECRET_KEYS[0], &lock_asm);
+    let result = run(&input_asm, &lock_asm, VerifyFlags::CONSENSUS);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::InvalidSignature(_, _)
+    ));
+    let result = run(
+        &input_asm,
+        &lock_asm,
+        VerifyFlags::CONSENSUS - VerifyFlags::NULLFAIL,
+    );
+    assert!(result.success, "{:?}", result.failure);
+}
+
+#[test]
+fn test_strict_pubkey_encoding() {
+    let result = run("OP_0", "<0x05> OP_CHECKSIG OP_NOT", VerifyFlags::CONSENSUS);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::InvalidPubKey(_)
+    ));
+    let flags = VerifyFlags::CONSENSUS - VerifyFlags::STRICTENC;
+    let result = run("OP_0", "<0x05> OP_CHECKSIG OP_NOT", flags);
+    assert!(result.success, "{:?}", result.failure);
+}
+
+#[test]
+fn test_sighash_forkid() {
+    let lock_asm = format!("{} OP_CHECKSIG", pubkey_asm(&SECRET_KEYS[0]));
+    // replace SIGHASH_ALL | SIGHASH_FORKID by SIGHASH_ALL
+    let input_asm = ecdsa_sig_asm(&SECRET_KEYS[0], &lock_asm).replace("41>", "01>");
+    let result = run(&input_asm, &lock_asm, VerifyFlags::CONSENSUS);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::MustUseForkId(0x01)
+    ));
+}
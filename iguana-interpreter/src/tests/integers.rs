@@ -0,0 +1,77 @@
+use bitcoin_cash::Opcode::*;
+
+use super::{run, script_error};
+use crate::{ScriptError, VerifyFlags};
+
+fn run_lock_script(lock_asm: &str, flags: VerifyFlags) -> Result<(), ScriptError> {
+    let result = run("", lock_asm, flags);
+    if result.success {
+        Ok(())
+    } else {
+        Err(script_error(result))
+    }
+}
+
+const WITHOUT_64BIT: VerifyFlags = VerifyFlags::from_bits_truncate(
+    VerifyFlags::STANDARD.bits() & !VerifyFlags::INTEGERS_64BIT.bits(),
+);
+
+#[test]
+fn test_64bit_arithmetic() {
+    let lock_asm = "<2147483647> OP_1ADD OP_1ADD <2147483649> OP_NUMEQUAL";
+    assert!(run_lock_script(lock_asm, VerifyFlags::STANDARD).is_ok());
+    // 4 byte operands may have a 5 byte result, which isn't a valid operand
+    assert!(matches!(
+        run_lock_script(lock_asm, WITHOUT_64BIT),
+        Err(ScriptError::ScriptNumOverflow(5, 4))
+    ));
+    assert!(matches!(
+        run_lock_script("<0x000000000000000001> OP_1ADD", VerifyFlags::STANDARD),
+        Err(ScriptError::ScriptNumOverflow(9, 8))
+    ));
+}
+
+#[test]
+fn test_64bit_overflow() {
+    assert!(matches!(
+        run_lock_script("<9223372036854775807> OP_1ADD", VerifyFlags::STANDARD),
+        Err(ScriptError::IntegerOverflow(OP_1ADD))
+    ));
+    assert!(matches!(
+        run_lock_script("<-9223372036854775807> OP_1SUB", VerifyFlags::STANDARD),
+        Err(ScriptError::IntegerOverflow(OP_1SUB))
+    ));
+    assert!(matches!(
+        run_lock_script("<4294967296> <4294967296> OP_MUL", VerifyFlags::STANDARD),
+        Err(ScriptError::IntegerOverflow(OP_MUL))
+    ));
+    let lock_asm = "<9223372036854775807> OP_NEGATE <-9223372036854775807> OP_NUMEQUAL";
+    assert!(run_lock_script(lock_asm, VerifyFlags::STANDARD).is_ok());
+}
+
+#[test]
+fn test_mul() {
+    let lock_asm = "OP_3 OP_1NEGATE OP_MUL <-3> OP_NUMEQUAL";
+    assert!(run_lock_script(lock_asm, VerifyFlags::STANDARD).is_ok());
+    assert!(matches!(
+        run_lock_script(lock_asm, WITHOUT_64BIT),
+        Err(ScriptError::DisabledOpcode(OP_MUL))
+    ));
+    // disabled opcodes fail even if they are not executed
+    assert!(matches!(
+        run_lock_script("OP_0 OP_IF OP_MUL OP_ENDIF OP_1", WITHOUT_64BIT),
+        Err(ScriptError::DisabledOpcode(OP_MUL))
+    ));
+}
+
+#[test]
+fn test_division_by_zero() {
+    assert!(matches!(
+        run_lock_script("OP_1 OP_0 OP_DIV", VerifyFlags::STANDARD),
+        Err(ScriptError::DivisionByZero(OP_DIV))
+    ));
+    assert!(matches!(
+        run_lock_script("OP_1 OP_0 OP_MOD", VerifyFlags::STANDARD),
+        Err(ScriptError::DivisionByZero(OP_MOD))
+    ));
+}
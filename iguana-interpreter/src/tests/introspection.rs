@@ -0,0 +1,104 @@
+use bitcoin_cash_ecc::init_ecc;
+use std::sync::Arc;
+
+use super::{run, run_in_context, script, script_error};
+use crate::{MockTxContext, ScriptError, ScriptInterpreter, VerifyFlags};
+
+const CONTEXT: MockTxContext = MockTxContext {
+    version: 2,
+    lock_time: 100,
+    sequence: 5,
+    value: 1000,
+};
+
+fn run_lock_script(lock_asm: &str) -> Result<(), ScriptError> {
+    let result = run_in_context("", lock_asm, CONTEXT, VerifyFlags::STANDARD);
+    if result.success {
+        Ok(())
+    } else {
+        Err(script_error(result))
+    }
+}
+
+#[test]
+fn test_tx_fields() {
+    assert!(run_lock_script("OP_INPUTINDEX OP_0 OP_NUMEQUAL").is_ok());
+    assert!(run_lock_script("OP_TXVERSION OP_2 OP_NUMEQUAL").is_ok());
+    assert!(run_lock_script("OP_TXINPUTCOUNT OP_1 OP_NUMEQUAL").is_ok());
+    assert!(run_lock_script("OP_TXOUTPUTCOUNT OP_1 OP_NUMEQUAL").is_ok());
+    assert!(run_lock_script("OP_TXLOCKTIME <100> OP_NUMEQUAL").is_ok());
+}
+
+#[test]
+fn test_input_fields() {
+    assert!(run_lock_script("OP_0 OP_UTXOVALUE <1000> OP_NUMEQUAL").is_ok());
+    assert!(run_lock_script("OP_0 OP_INPUTSEQUENCENUMBER OP_5 OP_NUMEQUAL").is_ok());
+    assert!(run_lock_script("OP_0 OP_OUTPOINTINDEX OP_0 OP_NUMEQUAL").is_ok());
+    assert!(run_lock_script("OP_0 OP_INPUTBYTECODE OP_0 OP_EQUAL").is_ok());
+    // the lock script is 7 bytes long
+    assert!(
+        run_lock_script("OP_0 OP_UTXOBYTECODE OP_SIZE OP_7 OP_NUMEQUALVERIFY OP_DROP OP_1").is_ok()
+    );
+    assert!(
+        run_lock_script("OP_ACTIVEBYTECODE OP_SIZE OP_6 OP_NUMEQUALVERIFY OP_DROP OP_1").is_ok()
+    );
+}
+
+#[test]
+fn test_output_fields() {
+    assert!(run_lock_script("OP_0 OP_OUTPUTVALUE OP_0 OP_NUMEQUAL").is_ok());
+    assert!(run_lock_script("OP_0 OP_OUTPUTBYTECODE OP_0 OP_EQUAL").is_ok());
+}
+
+#[test]
+fn test_invalid_index() {
+    assert!(matches!(
+        run_lock_script("OP_1 OP_UTXOVALUE"),
+        Err(ScriptError::InvalidInputIndex {
+            index: 1,
+            num_inputs: 1
+        })
+    ));
+    assert!(matches!(
+        run_lock_script("OP_1NEGATE OP_OUTPUTVALUE"),
+        Err(ScriptError::InvalidOutputIndex {
+            index: -1,
+            num_outputs: 1
+        })
+    ));
+    assert!(matches!(
+        run_lock_script("OP_UTXOVALUE"),
+        Err(ScriptError::IntrospectionStackEmpty("OP_UTXOVALUE"))
+    ));
+}
+
+#[test]
+fn test_introspection_disabled() {
+    let flags = VerifyFlags::STANDARD - VerifyFlags::NATIVE_INTROSPECTION;
+    let result = run_in_context("", "OP_INPUTINDEX", CONTEXT, flags);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::InvalidOpcode(0xc0)
+    ));
+    // token introspection also requires CashTokens
+    let flags = VerifyFlags::STANDARD - VerifyFlags::TOKENS;
+    let result = run("", "OP_0 OP_UTXOTOKENAMOUNT", flags);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::InvalidOpcode(0xd0)
+    ));
+}
+
+#[test]
+fn test_no_tx_context() {
+    let result = ScriptInterpreter::from_scripts(
+        script(""),
+        script("OP_TXVERSION"),
+        None,
+        Arc::new(init_ecc()),
+        VerifyFlags::STANDARD,
+    )
+    .unwrap()
+    .run();
+    assert!(matches!(script_error(result), ScriptError::NoTxContext));
+}
@@ -0,0 +1,87 @@
+use super::{ecdsa_sig_asm, interpreter, pubkey_asm, run, script_error, SECRET_KEYS};
+use crate::{ScriptError, VerifyFlags, MAX_OPS_PER_SCRIPT, MAX_SCRIPT_ELEMENT_SIZE};
+
+#[test]
+fn test_op_count() {
+    let lock_asm = |num_ops: usize| format!("OP_1{}", " OP_1 OP_DROP".repeat(num_ops));
+    let result = run("", &lock_asm(MAX_OPS_PER_SCRIPT), VerifyFlags::STANDARD);
+    assert!(result.success, "{:?}", result.failure);
+    let result = run("", &lock_asm(MAX_OPS_PER_SCRIPT + 1), VerifyFlags::STANDARD);
+    assert!(matches!(script_error(result), ScriptError::OpCount(202)));
+}
+
+#[test]
+fn test_op_count_unexecuted() {
+    // ops in unexecuted branches count too
+    let lock_asm = format!("OP_0 OP_IF{} OP_ENDIF OP_1", " OP_NOP".repeat(200));
+    let result = run("", &lock_asm, VerifyFlags::STANDARD);
+    assert!(matches!(script_error(result), ScriptError::OpCount(202)));
+}
+
+#[test]
+fn test_limits_tracked_without_enforcing() {
+    let lock_asm = format!("OP_0 OP_IF{} OP_ENDIF OP_1", " OP_NOP".repeat(200));
+    let mut interpreter = interpreter(
+        "",
+        &lock_asm,
+        VerifyFlags::STANDARD - VerifyFlags::ENFORCE_LIMITS,
+    );
+    let result = interpreter.run();
+    assert!(result.success, "{:?}", result.failure);
+    assert_eq!(interpreter.op_count(), 202);
+}
+
+#[test]
+fn test_stack_size() {
+    let result = run("", &"OP_1 ".repeat(1001), VerifyFlags::STANDARD);
+    assert!(matches!(script_error(result), ScriptError::StackSize(1001)));
+}
+
+#[test]
+fn test_element_size() {
+    let lock_asm = |size: usize| format!("<0x{}> OP_DROP OP_1", "00".repeat(size));
+    let result = run(
+        "",
+        &lock_asm(MAX_SCRIPT_ELEMENT_SIZE),
+        VerifyFlags::STANDARD,
+    );
+    assert!(result.success, "{:?}", result.failure);
+    let result = run(
+        "",
+        &lock_asm(MAX_SCRIPT_ELEMENT_SIZE + 1),
+        VerifyFlags::STANDARD,
+    );
+    assert!(matches!(script_error(result), ScriptError::PushSize(521)));
+}
+
+#[test]
+fn test_script_size() {
+    // 20 pushes of 520 bytes, each taking 523 bytes, followed by OP_DROP
+    let lock_asm = format!("<0x{}> OP_DROP ", "00".repeat(520)).repeat(20);
+    let result = run("", &lock_asm, VerifyFlags::STANDARD);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::ScriptSize(10_480)
+    ));
+}
+
+#[test]
+fn test_sig_checks() {
+    // legacy multisig counts a SigCheck per public key, but the short input script allows 3
+    let lock_asm = format!(
+        "OP_1 {} {} {} {} OP_4 OP_CHECKMULTISIG",
+        pubkey_asm(&SECRET_KEYS[0]),
+        pubkey_asm(&SECRET_KEYS[1]),
+        pubkey_asm(&SECRET_KEYS[2]),
+        pubkey_asm(&SECRET_KEYS[0]),
+    );
+    let input_asm = format!("OP_0 {}", ecdsa_sig_asm(&SECRET_KEYS[0], &lock_asm));
+    let result = run(&input_asm, &lock_asm, VerifyFlags::STANDARD);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::InputSigChecks {
+            sig_checks: 4,
+            limit: 3
+        }
+    ));
+}
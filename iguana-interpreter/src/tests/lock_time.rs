@@ -0,0 +1,107 @@
+use super::{run_in_context, script_error, stack_bytes};
+use crate::{MockTxContext, ScriptError, VerifyFlags};
+
+/// Runs `<required> OP_CHECKLOCKTIMEVERIFY` in a tx with `lock_time`, spending a non-final input.
+fn check_lock_time(required: &str, lock_time: u32) -> Result<(), ScriptError> {
+    let context = MockTxContext {
+        lock_time,
+        sequence: 0,
+        ..MockTxContext::default()
+    };
+    let lock_asm = format!("{} OP_CHECKLOCKTIMEVERIFY", required);
+    let result = run_in_context("", &lock_asm, context, VerifyFlags::STANDARD);
+    if result.success {
+        Ok(())
+    } else {
+        Err(script_error(result))
+    }
+}
+
+#[test]
+fn test_lock_time_satisfied() {
+    assert!(check_lock_time("<100>", 100).is_ok());
+    assert!(check_lock_time("<100>", 101).is_ok());
+    assert!(check_lock_time("<500000000>", 500_000_001).is_ok());
+}
+
+#[test]
+fn test_lock_time_unsatisfied() {
+    assert!(matches!(
+        check_lock_time("<101>", 100),
+        Err(ScriptError::LockTimeUnsatisfied {
+            required: 101,
+            lock_time: 100
+        })
+    ));
+}
+
+#[test]
+fn test_lock_time_type_mismatch() {
+    // block heights and timestamps can't be compared
+    assert!(matches!(
+        check_lock_time("<500000000>", 100),
+        Err(ScriptError::LockTimeTypeMismatch { .. })
+    ));
+    assert!(matches!(
+        check_lock_time("<100>", 500_000_000),
+        Err(ScriptError::LockTimeTypeMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_lock_time_negative() {
+    assert!(matches!(
+        check_lock_time("OP_1NEGATE", 100),
+        Err(ScriptError::NegativeLockTime(_, -1))
+    ));
+}
+
+#[test]
+fn test_lock_time_final_input() {
+    let context = MockTxContext {
+        lock_time: 100,
+        ..MockTxContext::default()
+    };
+    let result = run_in_context(
+        "",
+        "<100> OP_CHECKLOCKTIMEVERIFY",
+        context,
+        VerifyFlags::STANDARD,
+    );
+    assert!(matches!(
+        script_error(result),
+        ScriptError::LockTimeInputFinal
+    ));
+}
+
+#[test]
+fn test_lock_time_keeps_stack() {
+    let context = MockTxContext {
+        lock_time: 100,
+        sequence: 0,
+        ..MockTxContext::default()
+    };
+    let result = run_in_context(
+        "",
+        "<100> OP_CHECKLOCKTIMEVERIFY",
+        context,
+        VerifyFlags::STANDARD,
+    );
+    assert!(result.success, "{:?}", result.failure);
+    assert_eq!(stack_bytes(&result), vec![vec![100]]);
+}
+
+#[test]
+fn test_lock_time_without_flag() {
+    // the op is a NOP without the flag, which may be discouraged
+    let context = MockTxContext::default();
+    let flags = VerifyFlags::STANDARD - VerifyFlags::CHECKLOCKTIMEVERIFY;
+    let result = run_in_context("", "<101> OP_CHECKLOCKTIMEVERIFY", context, flags);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::DiscourageUpgradableNops(_)
+    ));
+    let flags = flags - VerifyFlags::DISCOURAGE_UPGRADABLE_NOPS;
+    let result = run_in_context("", "<101> OP_CHECKLOCKTIMEVERIFY", context, flags);
+    assert!(result.success, "{:?}", result.failure);
+}
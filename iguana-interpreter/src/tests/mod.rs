@@ -0,0 +1,170 @@
+//! Tests running whole scripts through the interpreter, written in assembly.
+
+mod cast_to_bool;
+mod checksig;
+mod code_separator;
+mod conditionals;
+mod integers;
+mod flags;
+mod introspection;
+mod limits;
+mod lock_time;
+mod multisig;
+mod p2sh;
+mod sequence;
+mod stack;
+mod verification;
+
+use bitcoin_cash::{
+    encoding_utils::{encode_bool, encode_int},
+    BitcoinCode, Hashed, Script, Sha256, Sha256d, SigHashFlags, StackItemData,
+};
+use bitcoin_cash_ecc::{init_ecc, SelectedECC};
+use secp256k1::{
+    curve::{Affine, Jacobian, Scalar, ECMULT_GEN_CONTEXT},
+    Message, PublicKey, SecretKey,
+};
+use std::convert::TryInto;
+use std::sync::Arc;
+
+use crate::{
+    script_from_asm, MockTxContext, ScriptError, ScriptInterpreter, VerificationFailure,
+    VerificationResult, VerifyFlags,
+};
+
+/// Secret keys the tests sign with.
+pub(crate) const SECRET_KEYS: [[u8; 32]; 3] = [[1; 32], [2; 32], [3; 32]];
+
+pub(crate) fn script(asm: &str) -> Script {
+    Script::from_ops(script_from_asm(asm).expect("Invalid asm"))
+}
+
+/// Interpreter for `input_asm` followed by `lock_asm`, run against the default mock tx.
+pub(crate) fn interpreter(
+    input_asm: &str,
+    lock_asm: &str,
+    flags: VerifyFlags,
+) -> ScriptInterpreter<SelectedECC> {
+    ScriptInterpreter::from_scripts(
+        script(input_asm),
+        script(lock_asm),
+        Some(MockTxContext::default()),
+        Arc::new(init_ecc()),
+        flags,
+    )
+    .expect("Invalid mock tx")
+}
+
+pub(crate) fn run(input_asm: &str, lock_asm: &str, flags: VerifyFlags) -> VerificationResult {
+    interpreter(input_asm, lock_asm, flags).run()
+}
+
+/// Runs `input_asm` followed by `lock_asm` against a mock tx with the fields of `context`.
+pub(crate) fn run_in_context(
+    input_asm: &str,
+    lock_asm: &str,
+    context: MockTxContext,
+    flags: VerifyFlags,
+) -> VerificationResult {
+    ScriptInterpreter::from_scripts(
+        script(input_asm),
+        script(lock_asm),
+        Some(context),
+        Arc::new(init_ecc()),
+        flags,
+    )
+    .expect("Invalid mock tx")
+    .run()
+}
+
+/// The script error the scripts fail with, panics if they fail otherwise or succeed.
+pub(crate) fn script_error(result: VerificationResult) -> ScriptError {
+    match result.failure {
+        Some(VerificationFailure::ScriptError(err)) => err,
+        failure => panic!("Expected a script error, got {:?}", failure),
+    }
+}
+
+/// The stack after evaluation, with each item encoded as when it is popped as byte array.
+pub(crate) fn stack_bytes(result: &VerificationResult) -> Vec<Vec<u8>> {
+    result
+        .stack
+        .iter()
+        .map(|item| match &item.data {
+            StackItemData::ByteArray(array) => array.to_vec(),
+            StackItemData::Integer(int) => encode_int(int.value()),
+            StackItemData::Boolean(boolean) => encode_bool(*boolean),
+        })
+        .collect()
+}
+
+/// Push of the compressed public key of `secret_key`.
+pub(crate) fn pubkey_asm(secret_key: &[u8; 32]) -> String {
+    let secret_key = SecretKey::parse(secret_key).unwrap();
+    let pubkey = PublicKey::from_secret_key(&secret_key).serialize_compressed();
+    format!("<0x{}>", hex::encode(&pubkey[..]))
+}
+
+/// Message signed with SIGHASH_ALL | SIGHASH_FORKID by the input of the mock tx, if the
+/// signing op runs with the scriptCode `script_code_asm`.
+fn sig_hash(script_code_asm: &str) -> [u8; 32] {
+    let interpreter = interpreter("", "", VerifyFlags::NONE);
+    let mut preimage = interpreter
+        .tx
+        .preimages(&[SigHashFlags::DEFAULT])
+        .swap_remove(0)
+        .swap_remove(0);
+    preimage.script_code = script(script_code_asm);
+    Sha256d::digest(preimage.ser())
+        .as_slice()
+        .try_into()
+        .unwrap()
+}
+
+/// Push of a DER encoded ECDSA signature of the mock tx, including the sighash byte.
+pub(crate) fn ecdsa_sig_asm(secret_key: &[u8; 32], script_code_asm: &str) -> String {
+    let secret_key = SecretKey::parse(secret_key).unwrap();
+    let (mut sig, _) = secp256k1::sign(&Message::parse(&sig_hash(script_code_asm)), &secret_key);
+    sig.normalize_s();
+    format!("<0x{}41>", hex::encode(sig.serialize_der().as_ref()))
+}
+
+/// Push of a Schnorr signature of the mock tx, including the sighash byte.
+pub(crate) fn schnorr_sig_asm(secret_key: &[u8; 32], script_code_asm: &str) -> String {
+    let sig = schnorr_sign(secret_key, &sig_hash(script_code_asm));
+    format!("<0x{}41>", hex::encode(sig))
+}
+
+/// Schnorr signature (r || s) of the 32 byte `msg_hash`. Follows the May 2019 specification,
+/// with a nonce derived from the key and the message.
+pub(crate) fn schnorr_sign(secret_key: &[u8; 32], msg_hash: &[u8; 32]) -> Vec<u8> {
+    let mut k = Scalar::default();
+    let _: bool = k
+        .set_b32(&sha256(&[&secret_key[..], &msg_hash[..]].concat()))
+        .into();
+    let mut big_r = Jacobian::default();
+    ECMULT_GEN_CONTEXT.ecmult_gen(&mut big_r, &k);
+    if !big_r.has_quad_y_var() {
+        k = -k;
+    }
+    let mut r = Affine::default();
+    r.set_gej(&big_r);
+    r.x.normalize();
+    let r_bytes = r.x.b32();
+
+    let secret_key = SecretKey::parse(secret_key).unwrap();
+    let pubkey = PublicKey::from_secret_key(&secret_key).serialize_compressed();
+    let mut e = Scalar::default();
+    let _: bool = e
+        .set_b32(&sha256(
+            &[&r_bytes[..], &pubkey[..], &msg_hash[..]].concat(),
+        ))
+        .into();
+    let x: Scalar = secret_key.into();
+    let s = k + e * x;
+    [r_bytes, s.b32()].concat()
+}
+
+pub(crate) fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data.to_vec()).as_slice().try_into().unwrap()
+}
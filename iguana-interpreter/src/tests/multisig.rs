@@ -0,0 +1,134 @@
+use super::{ecdsa_sig_asm, pubkey_asm, run, schnorr_sig_asm, script_error, SECRET_KEYS};
+use crate::{ScriptError, VerificationFailure, VerifyFlags};
+
+/// 2-of-3 multisig over the test keys.
+fn lock_asm() -> String {
+    format!(
+        "OP_2 {} {} {} OP_3 OP_CHECKMULTISIG",
+        pubkey_asm(&SECRET_KEYS[0]),
+        pubkey_asm(&SECRET_KEYS[1]),
+        pubkey_asm(&SECRET_KEYS[2]),
+    )
+}
+
+/// Input script with `dummy` and signatures of the given keys, signed with `sign`.
+fn input_asm(dummy: &str, key_indices: &[usize], sign: fn(&[u8; 32], &str) -> String) -> String {
+    let sigs = key_indices
+        .iter()
+        .map(|&idx| sign(&SECRET_KEYS[idx], &lock_asm()))
+        .collect::<Vec<_>>();
+    format!("{} {}", dummy, sigs.join(" "))
+}
+
+#[test]
+fn test_legacy_multisig() {
+    let result = run(
+        &input_asm("OP_0", &[0, 2], ecdsa_sig_asm),
+        &lock_asm(),
+        VerifyFlags::STANDARD,
+    );
+    assert!(result.success, "{:?}", result.failure);
+}
+
+#[test]
+fn test_legacy_multisig_wrong_order() {
+    // signatures have to be in the order of the public keys
+    let input = input_asm("OP_0", &[2, 0], ecdsa_sig_asm);
+    let result = run(&input, &lock_asm(), VerifyFlags::STANDARD);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::MultiSigNullFail
+    ));
+    let result = run(
+        &input,
+        &lock_asm(),
+        VerifyFlags::STANDARD - VerifyFlags::NULLFAIL,
+    );
+    assert!(matches!(
+        result.failure,
+        Some(VerificationFailure::TopOfStackFalse)
+    ));
+}
+
+#[test]
+fn test_legacy_multisig_rejects_schnorr_signature() {
+    let input = input_asm("OP_0", &[0, 1], schnorr_sig_asm);
+    let result = run(&input, &lock_asm(), VerifyFlags::STANDARD);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::SchnorrSignatureInLegacyMultisig(_)
+    ));
+}
+
+#[test]
+fn test_null_dummy() {
+    let flags = VerifyFlags::STANDARD - VerifyFlags::SCHNORR_MULTISIG;
+    let input = input_asm("<0x01>", &[0, 2], ecdsa_sig_asm);
+    let result = run(&input, &lock_asm(), flags);
+    assert!(matches!(script_error(result), ScriptError::NonNullDummy(_)));
+    let result = run(&input, &lock_asm(), flags - VerifyFlags::NULLDUMMY);
+    assert!(result.success, "{:?}", result.failure);
+}
+
+#[test]
+fn test_schnorr_multisig() {
+    // bit 0 selects the first public key, bit 2 the third
+    let result = run(
+        &input_asm("<0x05>", &[0, 2], schnorr_sig_asm),
+        &lock_asm(),
+        VerifyFlags::STANDARD,
+    );
+    assert!(result.success, "{:?}", result.failure);
+    let result = run(
+        &input_asm("<0x06>", &[1, 2], schnorr_sig_asm),
+        &lock_asm(),
+        VerifyFlags::STANDARD,
+    );
+    assert!(result.success, "{:?}", result.failure);
+}
+
+#[test]
+fn test_schnorr_multisig_wrong_key() {
+    let input = input_asm("<0x03>", &[0, 2], schnorr_sig_asm);
+    let result = run(&input, &lock_asm(), VerifyFlags::STANDARD);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::InvalidSignature(_, _)
+    ));
+}
+
+#[test]
+fn test_schnorr_multisig_invalid_bitfield() {
+    let run_bitfield = |bitfield: &str| {
+        let input = input_asm(bitfield, &[0, 2], schnorr_sig_asm);
+        script_error(run(&input, &lock_asm(), VerifyFlags::STANDARD))
+    };
+    assert!(matches!(
+        run_bitfield("<0x0500>"),
+        ScriptError::InvalidBitfieldSize {
+            expected: 1,
+            actual: 2
+        }
+    ));
+    assert!(matches!(
+        run_bitfield("<0x0d>"),
+        ScriptError::InvalidBitRange(3)
+    ));
+    assert!(matches!(
+        run_bitfield("<0x07>"),
+        ScriptError::InvalidBitCount {
+            expected: 2,
+            actual: 3
+        }
+    ));
+}
+
+#[test]
+fn test_schnorr_multisig_rejects_ecdsa_signature() {
+    let input = input_asm("<0x05>", &[0, 2], ecdsa_sig_asm);
+    let result = run(&input, &lock_asm(), VerifyFlags::STANDARD);
+    assert!(matches!(
+        script_error(result),
+        ScriptError::InvalidSchnorrSignatureLength(_)
+    ));
+}
@@ -0,0 +1,82 @@
+use bitcoin_cash::{
+    ByteArray, Hash160, Hashed, Sha256d, TxInput, TxOutpoint, TxOutput, UnhashedTx,
+};
+use bitcoin_cash_ecc::{init_ecc, SelectedECC};
+use std::sync::Arc;
+
+use super::{script, script_error};
+use crate::{P2shHashes, ScriptError, ScriptInterpreter, TxTokens, VerifyFlags};
+
+/// Interpreter for a P2SH input, where `redeem_asm` is the redeem script attached to the
+/// input and `script_hash` the hash of the spent output, if known.
+fn p2sh_interpreter(
+    input_asm: &str,
+    redeem_asm: &str,
+    script_hash: Option<ByteArray>,
+) -> ScriptInterpreter<SelectedECC> {
+    let tx = UnhashedTx {
+        version: 2,
+        inputs: vec![TxInput {
+            prev_out: TxOutpoint {
+                tx_hash: Sha256d::from_slice_le(&[0; 32]).unwrap(),
+                vout: 0,
+            },
+            script: script(input_asm),
+            sequence: 0xffff_ffff,
+            lock_script: Some(script(redeem_asm)),
+            value: Some(1000),
+            is_p2sh: Some(true),
+        }],
+        outputs: vec![TxOutput {
+            value: 0,
+            script: script(""),
+        }],
+        lock_time: 0,
+    };
+    ScriptInterpreter::new(Arc::new(tx.hashed()), 0, Arc::new(init_ecc()), VerifyFlags::STANDARD)
+        .unwrap()
+        .with_p2sh_hashes(P2shHashes { inputs: vec![script_hash] })
+}
+
+fn hash160(data: &[u8]) -> ByteArray {
+    Hash160::digest(data.to_vec()).into_byte_array()
+}
+
+#[test]
+fn test_p2sh_hash_match() {
+    // the redeem script OP_1 is pushed as <0x51>
+    let result = p2sh_interpreter("<0x51>", "OP_1", Some(hash160(&[0x51]))).run();
+    assert!(result.success, "{:?}", result.failure);
+}
+
+#[test]
+fn test_p2sh_hash_mismatch() {
+    // the input pushes OP_2 as redeem script, but the output commits to OP_1
+    let result = p2sh_interpreter("<0x52>", "OP_2", Some(hash160(&[0x51]))).run();
+    assert!(matches!(script_error(result), ScriptError::P2shHashMismatch));
+}
+
+#[test]
+fn test_p2sh_runs_pushed_redeem_script() {
+    // the attached redeem script differs, but the pushed one matches the output's hash
+    let mut interpreter = p2sh_interpreter("<0x51>", "OP_0", Some(hash160(&[0x51])));
+    let result = interpreter.run();
+    assert!(result.success, "{:?}", result.failure);
+}
+
+#[test]
+fn test_p2sh_hash_of_attached_redeem_script() {
+    let result = p2sh_interpreter("<0x51>", "OP_1", None).run();
+    assert!(result.success, "{:?}", result.failure);
+    let result = p2sh_interpreter("<0x52>", "OP_1", None).run();
+    assert!(matches!(script_error(result), ScriptError::P2shHashMismatch));
+}
+
+#[test]
+fn test_p2sh_redeem_script_keeps_token_prefix() {
+    // a redeem script which happens to start like a token prefix holding 5 fungible tokens
+    let redeem_asm = format!("OP_UNKNOWN_ef{} <0x05{}>", " OP_0".repeat(32), "51".repeat(15));
+    let interpreter = p2sh_interpreter("OP_0", &redeem_asm, None);
+    assert_eq!(interpreter.tokens().inputs, vec![None]);
+    assert_eq!(TxTokens::from_tx(&interpreter.tx).inputs, vec![None]);
+}
@@ -0,0 +1,86 @@
+use super::{run_in_context, script_error};
+use crate::{MockTxContext, ScriptError, VerifyFlags};
+
+/// Runs `<required> OP_CHECKSEQUENCEVERIFY` for an input with `sequence`.
+fn check_sequence(required: &str, sequence: u32) -> Result<(), ScriptError> {
+    let context = MockTxContext {
+        sequence,
+        ..MockTxContext::default()
+    };
+    let lock_asm = format!("{} OP_CHECKSEQUENCEVERIFY", required);
+    let result = run_in_context("", &lock_asm, context, VerifyFlags::STANDARD);
+    if result.success {
+        Ok(())
+    } else {
+        Err(script_error(result))
+    }
+}
+
+#[test]
+fn test_sequence_satisfied() {
+    assert!(check_sequence("<10>", 10).is_ok());
+    assert!(check_sequence("<10>", 11).is_ok());
+    // time based, in units of 512 seconds
+    assert!(check_sequence("<4194314>", 0x0040_000a).is_ok());
+}
+
+#[test]
+fn test_sequence_unsatisfied() {
+    assert!(matches!(
+        check_sequence("<10>", 9),
+        Err(ScriptError::SequenceUnsatisfied {
+            required: 10,
+            sequence: 9
+        })
+    ));
+}
+
+#[test]
+fn test_sequence_disable_flag() {
+    // a required sequence with the disable flag makes the op a NOP
+    assert!(check_sequence("<2147483658>", 0xffff_ffff).is_ok());
+    // but the input's sequence must not have it set
+    assert!(matches!(
+        check_sequence("<10>", 0x8000_000a),
+        Err(ScriptError::SequenceDisabled(0x8000_000a))
+    ));
+}
+
+#[test]
+fn test_sequence_type_mismatch() {
+    assert!(matches!(
+        check_sequence("<4194314>", 10),
+        Err(ScriptError::SequenceTypeMismatch { .. })
+    ));
+    assert!(matches!(
+        check_sequence("<10>", 0x0040_000a),
+        Err(ScriptError::SequenceTypeMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_sequence_negative() {
+    assert!(matches!(
+        check_sequence("OP_1NEGATE", 10),
+        Err(ScriptError::NegativeLockTime(_, -1))
+    ));
+}
+
+#[test]
+fn test_sequence_tx_version() {
+    let context = MockTxContext {
+        version: 1,
+        sequence: 10,
+        ..MockTxContext::default()
+    };
+    let result = run_in_context(
+        "",
+        "<10> OP_CHECKSEQUENCEVERIFY",
+        context,
+        VerifyFlags::STANDARD,
+    );
+    assert!(matches!(
+        script_error(result),
+        ScriptError::SequenceTxVersion(1)
+    ));
+}
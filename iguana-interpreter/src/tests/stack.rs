@@ -0,0 +1,45 @@
+use bitcoin_cash::Opcode::*;
+
+use super::{run, script_error};
+use crate::{ScriptError, VerificationFailure, VerifyFlags};
+
+fn run_error(lock_asm: &str) -> ScriptError {
+    script_error(run("", lock_asm, VerifyFlags::STANDARD))
+}
+
+#[test]
+fn test_stack_empty() {
+    assert!(matches!(
+        run_error("OP_TOALTSTACK"),
+        ScriptError::StackEmpty(OP_TOALTSTACK)
+    ));
+    assert!(matches!(
+        run_error("OP_SIZE"),
+        ScriptError::StackEmpty(OP_SIZE)
+    ));
+    assert!(matches!(
+        run_error("OP_1 OP_ADD"),
+        ScriptError::StackEmpty(OP_ADD)
+    ));
+    assert!(matches!(
+        run_error("OP_VERIFY"),
+        ScriptError::StackEmpty(OP_VERIFY)
+    ));
+}
+
+#[test]
+fn test_alt_stack_empty() {
+    assert!(matches!(
+        run_error("OP_1 OP_FROMALTSTACK"),
+        ScriptError::AltStackEmpty(OP_FROMALTSTACK)
+    ));
+}
+
+#[test]
+fn test_empty_scripts() {
+    let result = run("", "", VerifyFlags::STANDARD);
+    assert!(matches!(
+        result.failure,
+        Some(VerificationFailure::StackEmpty)
+    ));
+}
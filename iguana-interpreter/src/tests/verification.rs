@@ -0,0 +1,31 @@
+use super::{run, script_error, stack_bytes};
+use crate::{ScriptError, VerificationFailure, VerifyFlags};
+
+#[test]
+fn test_top_of_stack_decides() {
+    let flags = VerifyFlags::STANDARD - VerifyFlags::CLEANSTACK;
+    let result = run("OP_0 OP_1", "", flags);
+    assert!(result.success, "{:?}", result.failure);
+    assert_eq!(stack_bytes(&result), vec![vec![], vec![1]]);
+    let result = run("OP_1 OP_0", "", flags);
+    assert!(!result.success);
+    assert!(matches!(
+        result.failure,
+        Some(VerificationFailure::TopOfStackFalse)
+    ));
+}
+
+#[test]
+fn test_stack_at_failing_op() {
+    let result = run(
+        "",
+        "OP_1 OP_2 OP_VERIFY OP_0 OP_VERIFY OP_3",
+        VerifyFlags::STANDARD,
+    );
+    assert!(!result.success);
+    assert!(matches!(
+        script_error(result.clone()),
+        ScriptError::VerifyFailed
+    ));
+    assert_eq!(stack_bytes(&result), vec![vec![1]]);
+}
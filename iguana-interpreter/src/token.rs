@@ -0,0 +1,337 @@
+//! CashTokens token data, which is encoded as prefix of an output's locking bytecode.
+
+use bitcoin_cash::{deserialize_ops, ByteArray, Script, Tx};
+use std::convert::TryInto;
+
+use crate::{serialize_script, ScriptError};
+
+/// First byte of the token prefix, which is an unassigned opcode.
+pub const PREFIX_TOKEN: u8 = 0xef;
+
+/// Length of a token category ID.
+pub const TOKEN_CATEGORY_LEN: usize = 32;
+
+/// Maximum length of an NFT commitment.
+pub const MAX_NFT_COMMITMENT_LEN: usize = 40;
+
+const HAS_COMMITMENT_LENGTH: u8 = 0x40;
+const HAS_NFT: u8 = 0x20;
+const HAS_AMOUNT: u8 = 0x10;
+const RESERVED_BIT: u8 = 0x80;
+const CAPABILITY_MASK: u8 = 0x0f;
+
+/// Capability of a non-fungible token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NftCapability {
+    /// The commitment can't be changed.
+    None,
+    /// The NFT can be replaced by one NFT with a different commitment.
+    Mutable,
+    /// The NFT can create new NFTs of the same category.
+    Minting,
+}
+
+/// A non-fungible token.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Nft {
+    pub capability: NftCapability,
+    pub commitment: ByteArray,
+}
+
+/// The tokens held by an output.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenData {
+    /// Category ID, in the byte order of the encoding (like the hashes of outpoints).
+    pub category: ByteArray,
+    /// Fungible token amount, 0 if the output holds no fungible tokens.
+    pub amount: u64,
+    pub nft: Option<Nft>,
+}
+
+/// Token data of the UTXOs spent by and the outputs created by a tx.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TxTokens {
+    pub inputs: Vec<Option<TokenData>>,
+    pub outputs: Vec<Option<TokenData>>,
+}
+
+impl NftCapability {
+    fn from_u8(capability: u8) -> Option<Self> {
+        match capability {
+            0x00 => Some(NftCapability::None),
+            0x01 => Some(NftCapability::Mutable),
+            0x02 => Some(NftCapability::Minting),
+            _ => None,
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            NftCapability::None => 0x00,
+            NftCapability::Mutable => 0x01,
+            NftCapability::Minting => 0x02,
+        }
+    }
+}
+
+impl TokenData {
+    /// Category ID with the capability byte appended for mutable and minting NFTs, as pushed
+    /// by OP_UTXOTOKENCATEGORY and OP_OUTPUTTOKENCATEGORY.
+    pub fn category_with_capability(&self) -> ByteArray {
+        let mut category = self.category.to_vec();
+        match &self.nft {
+            Some(nft) if nft.capability != NftCapability::None => {
+                category.push(nft.capability.to_u8());
+            }
+            _ => {}
+        }
+        category.into()
+    }
+
+    /// Commitment of the NFT, empty if there is no NFT.
+    pub fn commitment(&self) -> ByteArray {
+        match &self.nft {
+            Some(nft) => nft.commitment.clone(),
+            None => ByteArray::from(Vec::new()),
+        }
+    }
+}
+
+impl TxTokens {
+    /// Reads the token prefixes of the lock scripts of the tx's inputs and of its outputs.
+    /// The lock script of P2SH inputs is the redeem script, which has no prefix, so the
+    /// tokens of their spent outputs have to be set separately. Inputs without lock script
+    /// are assumed to hold no tokens.
+    pub fn from_tx(tx: &Tx) -> Self {
+        TxTokens {
+            inputs: tx
+                .inputs()
+                .iter()
+                .map(|input| match (&input.lock_script, input.is_p2sh) {
+                    (Some(lock_script), Some(false) | None) => script_token(lock_script),
+                    _ => None,
+                })
+                .collect(),
+            outputs: tx
+                .outputs()
+                .iter()
+                .map(|output| script_token(&output.script))
+                .collect(),
+        }
+    }
+}
+
+/// Token data of the script, if it has a valid token prefix.
+pub fn script_token(script: &Script) -> Option<TokenData> {
+    let bytecode = serialize_script(script).ok()?;
+    split_token_prefix(&bytecode).ok()?.0
+}
+
+/// Splits the token prefix off a lock script, returning the token data and the script that
+/// is actually evaluated. Scripts without prefix are returned as is, keeping their op names.
+pub fn split_script_token(script: &Script) -> Result<(Option<TokenData>, Script), ScriptError> {
+    let bytecode = serialize_script(script)?;
+    match split_token_prefix(&bytecode)? {
+        (None, _) => Ok((None, script.clone())),
+        (Some(token), remaining) => {
+            let ops = deserialize_ops(remaining).map_err(|err| {
+                ScriptError::InvalidTokenPrefix(format!("invalid bytecode after prefix: {}", err).into())
+            })?;
+            Ok((Some(token), Script::from_ops(ops)))
+        }
+    }
+}
+
+/// Splits the locking bytecode of an output into its token data and the actual bytecode.
+/// Bytecode not starting with PREFIX_TOKEN has no token data.
+pub fn split_token_prefix(bytecode: &[u8]) -> Result<(Option<TokenData>, &[u8]), ScriptError> {
+    if bytecode.first() != Some(&PREFIX_TOKEN) {
+        return Ok((None, bytecode));
+    }
+    let mut remaining = &bytecode[1..];
+    let category = take(&mut remaining, TOKEN_CATEGORY_LEN)?.to_vec().into();
+    let bitfield = take(&mut remaining, 1)?[0];
+    if bitfield & RESERVED_BIT != 0 {
+        return Err(invalid_prefix("reserved bit is set"));
+    }
+    let has_nft = bitfield & HAS_NFT != 0;
+    let has_amount = bitfield & HAS_AMOUNT != 0;
+    let has_commitment = bitfield & HAS_COMMITMENT_LENGTH != 0;
+    let capability = NftCapability::from_u8(bitfield & CAPABILITY_MASK)
+        .ok_or_else(|| invalid_prefix("invalid NFT capability"))?;
+    if !has_nft && !has_amount {
+        return Err(invalid_prefix("token has neither NFT nor amount"));
+    }
+    if !has_nft && (has_commitment || capability != NftCapability::None) {
+        return Err(invalid_prefix("commitment or capability without NFT"));
+    }
+    let commitment = if has_commitment {
+        let commitment_len = read_compact_size(&mut remaining)?;
+        if commitment_len == 0 || commitment_len > MAX_NFT_COMMITMENT_LEN as u64 {
+            return Err(invalid_prefix("invalid commitment length"));
+        }
+        take(&mut remaining, commitment_len as usize)?.to_vec()
+    } else {
+        Vec::new()
+    };
+    let amount = if has_amount {
+        let amount = read_compact_size(&mut remaining)?;
+        if amount == 0 || amount > i64::MAX as u64 {
+            return Err(invalid_prefix("invalid amount"));
+        }
+        amount
+    } else {
+        0
+    };
+    let nft = if has_nft {
+        Some(Nft {
+            capability,
+            commitment: commitment.into(),
+        })
+    } else {
+        None
+    };
+    Ok((Some(TokenData { category, amount, nft }), remaining))
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], ScriptError> {
+    if data.len() < len {
+        return Err(invalid_prefix("unexpected end of prefix"));
+    }
+    let (taken, remaining) = data.split_at(len);
+    *data = remaining;
+    Ok(taken)
+}
+
+fn read_compact_size(data: &mut &[u8]) -> Result<u64, ScriptError> {
+    let first = take(data, 1)?[0];
+    let (value, min_value) = match first {
+        0xfd => (u16::from_le_bytes(take(data, 2)?.try_into().unwrap()) as u64, 0xfd),
+        0xfe => (u32::from_le_bytes(take(data, 4)?.try_into().unwrap()) as u64, 0x1_0000),
+        0xff => (u64::from_le_bytes(take(data, 8)?.try_into().unwrap()), 0x1_0000_0000),
+        value => return Ok(value as u64),
+    };
+    if value < min_value {
+        return Err(invalid_prefix("non-minimal compact size"));
+    }
+    Ok(value)
+}
+
+fn invalid_prefix(msg: &'static str) -> ScriptError {
+    ScriptError::InvalidTokenPrefix(msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CATEGORY: [u8; TOKEN_CATEGORY_LEN] = [0x11; TOKEN_CATEGORY_LEN];
+
+    /// Token prefix with the given bitfield, followed by `rest`.
+    fn prefix(bitfield: u8, rest: &[u8]) -> Vec<u8> {
+        let mut bytecode = vec![PREFIX_TOKEN];
+        bytecode.extend_from_slice(&CATEGORY);
+        bytecode.push(bitfield);
+        bytecode.extend_from_slice(rest);
+        bytecode
+    }
+
+    fn amount(amount_ser: &[u8]) -> Result<u64, ScriptError> {
+        let bytecode = prefix(HAS_AMOUNT, amount_ser);
+        let (token, _) = split_token_prefix(&bytecode)?;
+        Ok(token.unwrap().amount)
+    }
+
+    #[test]
+    fn test_no_prefix() {
+        let (token, remaining) = split_token_prefix(&[0x76, 0xa9]).unwrap();
+        assert_eq!(token, None);
+        assert_eq!(remaining, &[0x76, 0xa9]);
+    }
+
+    #[test]
+    fn test_fungible_token() {
+        let bytecode = prefix(HAS_AMOUNT, &[0x05, 0xac]);
+        let (token, remaining) = split_token_prefix(&bytecode).unwrap();
+        let token = token.unwrap();
+        assert_eq!(token.category.to_vec(), CATEGORY.to_vec());
+        assert_eq!(token.amount, 5);
+        assert_eq!(token.nft, None);
+        assert_eq!(remaining, &[0xac]);
+    }
+
+    #[test]
+    fn test_nft_with_commitment() {
+        let bytecode = prefix(HAS_NFT | HAS_COMMITMENT_LENGTH | 0x02, &[0x02, 0xaa, 0xbb, 0x51]);
+        let (token, remaining) = split_token_prefix(&bytecode).unwrap();
+        let nft = token.unwrap().nft.unwrap();
+        assert_eq!(nft.capability, NftCapability::Minting);
+        assert_eq!(nft.commitment.to_vec(), vec![0xaa, 0xbb]);
+        assert_eq!(remaining, &[0x51]);
+    }
+
+    #[test]
+    fn test_invalid_bitfield() {
+        assert!(split_token_prefix(&prefix(RESERVED_BIT | HAS_AMOUNT, &[0x01])).is_err());
+        assert!(split_token_prefix(&prefix(HAS_NFT | 0x03, &[])).is_err());
+        assert!(split_token_prefix(&prefix(0x00, &[])).is_err());
+        assert!(split_token_prefix(&prefix(HAS_AMOUNT | 0x01, &[0x01])).is_err());
+        assert!(
+            split_token_prefix(&prefix(HAS_AMOUNT | HAS_COMMITMENT_LENGTH, &[0x01, 0xaa, 0x01]))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_truncated_prefix() {
+        assert!(split_token_prefix(&[PREFIX_TOKEN, 0x11]).is_err());
+        assert!(split_token_prefix(&prefix(HAS_AMOUNT, &[])).is_err());
+        assert!(split_token_prefix(&prefix(HAS_NFT | HAS_COMMITMENT_LENGTH, &[0x02, 0xaa])).is_err());
+    }
+
+    #[test]
+    fn test_commitment_length() {
+        let bitfield = HAS_NFT | HAS_COMMITMENT_LENGTH;
+        assert!(split_token_prefix(&prefix(bitfield, &[0x00])).is_err());
+
+        let mut commitment = vec![MAX_NFT_COMMITMENT_LEN as u8];
+        commitment.extend_from_slice(&[0xaa; MAX_NFT_COMMITMENT_LEN]);
+        let bytecode = prefix(bitfield, &commitment);
+        let (token, remaining) = split_token_prefix(&bytecode).unwrap();
+        assert_eq!(token.unwrap().commitment().len(), MAX_NFT_COMMITMENT_LEN);
+        assert!(remaining.is_empty());
+
+        let mut commitment = vec![MAX_NFT_COMMITMENT_LEN as u8 + 1];
+        commitment.extend_from_slice(&[0xaa; MAX_NFT_COMMITMENT_LEN + 1]);
+        assert!(split_token_prefix(&prefix(bitfield, &commitment)).is_err());
+    }
+
+    #[test]
+    fn test_compact_size() {
+        assert_eq!(amount(&[0xfc]).unwrap(), 0xfc);
+        assert_eq!(amount(&[0xfd, 0xfd, 0x00]).unwrap(), 0xfd);
+        assert_eq!(amount(&[0xfe, 0x00, 0x00, 0x01, 0x00]).unwrap(), 0x1_0000);
+        assert_eq!(
+            amount(&[0xff, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00]).unwrap(),
+            0x1_0000_0000,
+        );
+        assert_eq!(
+            amount(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]).unwrap(),
+            i64::MAX as u64,
+        );
+    }
+
+    #[test]
+    fn test_non_minimal_compact_size() {
+        assert!(amount(&[0xfd, 0xfc, 0x00]).is_err());
+        assert!(amount(&[0xfe, 0xff, 0xff, 0x00, 0x00]).is_err());
+        assert!(amount(&[0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_amount() {
+        assert!(amount(&[0x00]).is_err());
+        assert!(amount(&[0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80]).is_err());
+        assert!(amount(&[0xfd, 0x01]).is_err());
+    }
+}
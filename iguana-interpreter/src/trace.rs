@@ -0,0 +1,176 @@
+//! Recording of a complete run of the interpreter, which can be stored as JSON and replayed
+//! without rerunning the interpreter.
+
+use bitcoin_cash::{StackItemData, StackItemDelta, ECC};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    op_to_asm, ScriptInterpreter, ScriptPhase, SignatureScheme, StackItem,
+    VerificationFailure, VerificationResult,
+};
+
+/// All ops run by the interpreter, from the state when recording started until the end of
+/// the lock script or the first failing op.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Trace {
+    pub initial_stack: Vec<TraceItem>,
+    pub initial_alt_stack: Vec<TraceItem>,
+    pub steps: Vec<TraceStep>,
+    pub success: bool,
+    /// Why the verification failed, `None` on success.
+    pub failure: Option<String>,
+}
+
+/// A single op and its effect on the stacks.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TraceStep {
+    pub phase: ScriptPhase,
+    pub instruction_pointer: usize,
+    /// The op in assembly, see `op_to_asm`.
+    pub op: String,
+    pub src_file: String,
+    pub src_line: u32,
+    /// Whether the op was in an executed branch. Unexecuted ops only affect the conditionals.
+    pub executed: bool,
+    pub stack: StackDelta,
+    pub alt_stack: StackDelta,
+    /// Error message if the op failed, in which case it is the last step.
+    pub error: Option<String>,
+}
+
+/// Change of a stack by an op: `num_removed` items are popped off, then `pushed` are pushed.
+/// `deltas` marks the remaining items which the op moved or observed, by index from the bottom.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StackDelta {
+    pub num_removed: usize,
+    pub pushed: Vec<TraceItem>,
+    pub deltas: Vec<(usize, String)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TraceItem {
+    pub data: TraceData,
+    pub name: Option<String>,
+    pub delta: String,
+    pub sig_scheme: Option<SignatureScheme>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TraceData {
+    Integer(i32),
+    Boolean(bool),
+    /// Hex encoded byte array.
+    ByteArray(String),
+}
+
+impl Trace {
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Stack and alt stack after the first `num_steps` steps.
+    pub fn stacks_at(&self, num_steps: usize) -> (Vec<TraceItem>, Vec<TraceItem>) {
+        let mut stack = self.initial_stack.clone();
+        let mut alt_stack = self.initial_alt_stack.clone();
+        for step in self.steps.iter().take(num_steps) {
+            step.stack.apply(&mut stack);
+            step.alt_stack.apply(&mut alt_stack);
+        }
+        (stack, alt_stack)
+    }
+}
+
+impl StackDelta {
+    fn between(before: &[StackItem], after: &[StackItem]) -> Self {
+        let num_kept = before
+            .iter()
+            .zip(after)
+            .take_while(|(a, b)| a.data == b.data && a.name == b.name)
+            .count();
+        StackDelta {
+            num_removed: before.len() - num_kept,
+            pushed: after[num_kept..].iter().map(TraceItem::from_item).collect(),
+            deltas: after[..num_kept]
+                .iter()
+                .enumerate()
+                .filter(|(_, item)| item.delta != StackItemDelta::Untouched)
+                .map(|(idx, item)| (idx, format!("{:?}", item.delta)))
+                .collect(),
+        }
+    }
+
+    pub fn apply(&self, stack: &mut Vec<TraceItem>) {
+        let num_kept = stack.len().saturating_sub(self.num_removed);
+        stack.truncate(num_kept);
+        for item in stack.iter_mut() {
+            item.delta = format!("{:?}", StackItemDelta::Untouched);
+        }
+        for (idx, delta) in &self.deltas {
+            if let Some(item) = stack.get_mut(*idx) {
+                item.delta = delta.clone();
+            }
+        }
+        stack.extend(self.pushed.iter().cloned());
+    }
+}
+
+impl TraceItem {
+    fn from_item(item: &StackItem) -> Self {
+        TraceItem {
+            data: match &item.data {
+                StackItemData::Integer(int) => TraceData::Integer(int.value()),
+                StackItemData::Boolean(boolean) => TraceData::Boolean(*boolean),
+                StackItemData::ByteArray(array) => TraceData::ByteArray(hex::encode(&array[..])),
+            },
+            name: item.name.as_ref().map(|name| name.to_string()),
+            delta: format!("{:?}", item.delta),
+            sig_scheme: item.sig_scheme,
+        }
+    }
+}
+
+impl<E: ECC> ScriptInterpreter<E> {
+    /// Runs all remaining ops, recording each of them, and verifies the input like `run`.
+    pub fn trace(&mut self) -> Trace {
+        let mut trace = Trace {
+            initial_stack: self.stack.iter().map(TraceItem::from_item).collect(),
+            initial_alt_stack: self.alt_stack.iter().map(TraceItem::from_item).collect(),
+            ..Trace::default()
+        };
+        while !self.is_finished() {
+            let stack_before = self.stack.clone();
+            let alt_stack_before = self.alt_stack.clone();
+            // only an empty input script has no op at the instruction pointer
+            let tagged_op = self.script.ops_arc().get(self.instruction_pointer);
+            let mut step = TraceStep {
+                phase: self.phase,
+                instruction_pointer: self.instruction_pointer,
+                op: tagged_op.map(|op| op_to_asm(&op.op)).unwrap_or_default(),
+                src_file: tagged_op.map(|op| op.src_file.to_string()).unwrap_or_default(),
+                src_line: tagged_op.map(|op| op.src_line).unwrap_or_default(),
+                executed: self.exec_stack.iter().all(|&x| x),
+                stack: StackDelta::default(),
+                alt_stack: StackDelta::default(),
+                error: None,
+            };
+            let result = self.run_next_op();
+            step.stack = StackDelta::between(&stack_before, &self.stack);
+            step.alt_stack = StackDelta::between(&alt_stack_before, &self.alt_stack);
+            if let Err(err) = result {
+                step.error = Some(err.to_string());
+                trace.steps.push(step);
+                trace.failure = Some(VerificationFailure::from(err).to_string());
+                return trace;
+            }
+            trace.steps.push(step);
+        }
+        let VerificationResult { success, failure, .. } = self.run();
+        trace.success = success;
+        trace.failure = failure.map(|failure| failure.to_string());
+        trace
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{ScriptError, StackItem};
+
+/// Outcome of evaluating an input with `ScriptInterpreter::run`.
+#[derive(Clone, Debug)]
+pub struct VerificationResult {
+    /// Whether the input would be accepted under the interpreter's flags.
+    pub success: bool,
+    /// The stack after evaluation stopped, either at the end or at the failing op.
+    pub stack: Vec<StackItem>,
+    /// Why the input failed, `None` on success.
+    pub failure: Option<VerificationFailure>,
+}
+
+#[derive(Error, Clone, Debug)]
+pub enum VerificationFailure {
+    #[error("{0}")]
+    ScriptError(ScriptError),
+
+    #[error("Stack empty after evaluation")]
+    StackEmpty,
+
+    #[error("Top of stack is false")]
+    TopOfStackFalse,
+
+    #[error("Stack not clean: {0} items left, expected exactly 1")]
+    StackNotClean(usize),
+
+    #[error("Script ended with {0} unterminated OP_IF/OP_NOTIF")]
+    UnterminatedIf(usize),
+}
+
+impl From<ScriptError> for VerificationFailure {
+    fn from(err: ScriptError) -> Self {
+        match err {
+            ScriptError::UnterminatedConditionals(num) => VerificationFailure::UnterminatedIf(num),
+            err => VerificationFailure::ScriptError(err),
+        }
+    }
+}
+
+impl VerificationResult {
+    pub fn succeeded(stack: Vec<StackItem>) -> Self {
+        VerificationResult {
+            success: true,
+            stack,
+            failure: None,
+        }
+    }
+
+    pub fn failed(stack: Vec<StackItem>, failure: impl Into<VerificationFailure>) -> Self {
+        VerificationResult {
+            success: false,
+            stack,
+            failure: Some(failure.into()),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Verification of all inputs of a tx.
+
+use bitcoin_cash::{Tx, ECC};
+use std::sync::Arc;
+
+use crate::{
+    P2shHashes, ResourceUsage, ScriptError, ScriptInterpreter, TxTokens, VerificationFailure, VerifyFlags,
+};
+
+/// Verifies every input of a tx, each with its own `ScriptInterpreter`.
+pub struct TxVerifier<E: ECC> {
+    tx: Arc<Tx>,
+    ecc: Arc<E>,
+    flags: VerifyFlags,
+    tokens: Option<TxTokens>,
+    p2sh_hashes: Option<P2shHashes>,
+}
+
+/// Outcome of verifying a single input.
+#[derive(Clone, Debug)]
+pub struct InputReport {
+    pub input_idx: usize,
+    pub success: bool,
+    /// Why the input failed, `None` on success.
+    pub failure: Option<VerificationFailure>,
+    /// Resources used by the input, where `op_count` is the one of the last script evaluated.
+    pub usage: ResourceUsage,
+}
+
+/// Outcome of verifying all inputs of a tx, in input order.
+#[derive(Clone, Debug)]
+pub struct TxReport {
+    pub inputs: Vec<InputReport>,
+}
+
+impl TxReport {
+    /// Whether all inputs are valid.
+    pub fn success(&self) -> bool {
+        self.inputs.iter().all(|input| input.success)
+    }
+}
+
+impl<E: ECC> TxVerifier<E> {
+    pub fn new(tx: Arc<Tx>, ecc: Arc<E>, flags: VerifyFlags) -> Self {
+        TxVerifier {
+            tx,
+            ecc,
+            flags,
+            tokens: None,
+            p2sh_hashes: None,
+        }
+    }
+
+    /// Token data passed on to each interpreter, see `ScriptInterpreter::with_tokens`.
+    pub fn with_tokens(mut self, tokens: TxTokens) -> Self {
+        self.tokens = Some(tokens);
+        self
+    }
+
+    /// Script hashes passed on to each interpreter, see `ScriptInterpreter::with_p2sh_hashes`.
+    pub fn with_p2sh_hashes(mut self, p2sh_hashes: P2shHashes) -> Self {
+        self.p2sh_hashes = Some(p2sh_hashes);
+        self
+    }
+
+    /// Runs the interpreter for a single input.
+    pub fn verify_input(&self, input_idx: usize) -> InputReport {
+        let interpreter =
+            ScriptInterpreter::new(Arc::clone(&self.tx), input_idx, Arc::clone(&self.ecc), self.flags);
+        let mut interpreter = match interpreter {
+            Ok(interpreter) => interpreter,
+            Err(err) => {
+                return InputReport {
+                    input_idx,
+                    success: false,
+                    failure: Some(ScriptError::from(err).into()),
+                    usage: ResourceUsage::default(),
+                }
+            }
+        };
+        if let Some(tokens) = &self.tokens {
+            interpreter = interpreter.with_tokens(tokens.clone());
+        }
+        if let Some(p2sh_hashes) = &self.p2sh_hashes {
+            interpreter = interpreter.with_p2sh_hashes(p2sh_hashes.clone());
+        }
+        let result = interpreter.run();
+        InputReport {
+            input_idx,
+            success: result.success,
+            failure: result.failure,
+            usage: interpreter.resource_usage(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<E: ECC + Send + Sync> TxVerifier<E> {
+    /// Verifies all inputs, spread over as many threads as there are CPUs.
+    pub fn verify_all(&self) -> TxReport {
+        let num_inputs = self.tx.inputs().len();
+        if num_inputs == 0 {
+            return TxReport { inputs: Vec::new() };
+        }
+        let num_threads = std::thread::available_parallelism()
+            .map(|num| num.get())
+            .unwrap_or(1)
+            .min(num_inputs);
+        let chunk_size = num_inputs.div_ceil(num_threads);
+        let input_indices = (0..num_inputs).collect::<Vec<_>>();
+        let inputs = std::thread::scope(|scope| {
+            let threads = input_indices
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk.iter().map(|&idx| self.verify_input(idx)).collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            threads
+                .into_iter()
+                .flat_map(|thread| thread.join().expect("Verifier thread panicked"))
+                .collect()
+        });
+        TxReport { inputs }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl<E: ECC> TxVerifier<E> {
+    /// Verifies all inputs one after another, as there are no threads on wasm.
+    pub fn verify_all(&self) -> TxReport {
+        TxReport {
+            inputs: (0..self.tx.inputs().len())
+                .map(|idx| self.verify_input(idx))
+                .collect(),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-import { Tx, ScriptError, Interpreter, ECC, setPanicHook } from "iguana-lib";
+import { Tx, ScriptError, Interpreter, ECC, setPanicHook, standardVerifyFlags } from "iguana-lib";
 import { RootState } from "../../state/rootstate";
 import { RootAction } from "../../state/rootaction";
 import { UPDATE_TRANSACTION, ADD_WEB_SOCKET_TRANSACTION, INIT_ECC, WASM_READY } from "./actions";
@@ -26,7 +26,17 @@ function deleteTx(tx: Transaction) {
 function evalTransaction(ecc: ECC, tx: Tx, name: string | undefined, source: TransactionSource): Transaction {
   const inputs: TransactionInput[] = [];
   for (const input of tx.inputs()) {
-    const interpreter = new Interpreter(ecc, input);
+    let interpreter: Interpreter;
+    try {
+      interpreter = new Interpreter(ecc, input, standardVerifyFlags());
+    } catch (err) {
+      if (!(err instanceof ScriptError)) {
+        throw err;
+      }
+      // e.g. the input's UTXO is unknown, show the error instead of a trace
+      inputs.push(TransactionInputFactory({txInput: input, error: err}));
+      continue;
+    }
     const trace: TraceItem[] = [];
     let error: ScriptError | undefined = undefined;
     trace.push(TraceItemFactory({
@@ -1,19 +1,18 @@
 use wasm_bindgen::prelude::*;
 use bitcoin_cash::StackItemData;
 use bitcoin_cash_ecc::{SelectedECC, init_ecc};
-use crate::{TxInput, ByteArray, Op};
+use crate::{TxInput, ByteArray, Op, Script};
 use std::sync::Arc;
 
-use iguana_interpreter::ScriptInterpreter;
+use iguana_interpreter::{Breakpoint, ConstructionError, MockTxContext, ScriptInterpreter, ScriptPhase, SignatureScheme, VerifyFlags};
 
 #[wasm_bindgen]
 pub struct Interpreter {
     interpreter: ScriptInterpreter<SelectedECC>,
-    input: TxInput,
 }
 
 #[wasm_bindgen]
-pub struct ScriptError(iguana_interpreter::ScriptError);
+pub struct ScriptError(pub(crate) iguana_interpreter::ScriptError);
 
 #[wasm_bindgen]
 pub struct Stack {
@@ -27,7 +26,20 @@ pub struct StackItem {
 }
 
 #[wasm_bindgen]
-pub struct ECC(Arc<SelectedECC>);
+pub struct SigScriptCode(iguana_interpreter::SigScriptCode);
+
+#[wasm_bindgen]
+pub struct StopReason(iguana_interpreter::StopReason);
+
+#[wasm_bindgen]
+pub struct VerificationResult(iguana_interpreter::VerificationResult);
+
+/// Tx fields for scripts evaluated with `Interpreter.fromScripts`.
+#[wasm_bindgen]
+pub struct TxContext(MockTxContext);
+
+#[wasm_bindgen]
+pub struct ECC(pub(crate) Arc<SelectedECC>);
 
 #[wasm_bindgen]
 impl ECC {
@@ -37,26 +49,182 @@ impl ECC {
     }
 }
 
+#[wasm_bindgen]
+impl TxContext {
+    #[wasm_bindgen(constructor)]
+    pub fn new(version: i32, lock_time: u32, sequence: u32, value: u64) -> TxContext {
+        TxContext(MockTxContext { version, lock_time, sequence, value })
+    }
+}
+
+#[wasm_bindgen(js_name = standardVerifyFlags)]
+pub fn standard_verify_flags() -> u32 {
+    VerifyFlags::STANDARD.bits()
+}
+
+#[wasm_bindgen(js_name = consensusVerifyFlags)]
+pub fn consensus_verify_flags() -> u32 {
+    VerifyFlags::CONSENSUS.bits()
+}
+
 #[wasm_bindgen]
 impl Interpreter {
     #[wasm_bindgen(constructor)]
-    pub fn new(ecc: &ECC, input: &TxInput) -> Interpreter {
-        let mut interpreter = ScriptInterpreter::new(
+    pub fn new(ecc: &ECC, input: &TxInput, flags: u32) -> Result<Interpreter, ScriptError> {
+        let interpreter = ScriptInterpreter::new(
             input.tx(),
             input.input_idx(),
             Arc::clone(&ecc.0),
-        );
-        interpreter.push_input_data().expect("Could not push input data");
-        Interpreter {
-            interpreter,
-            input: input.clone(),
-        }
+            VerifyFlags::from_bits_truncate(flags),
+        ).map_err(|err| ScriptError(err.into()))?
+        .with_tokens(input.tokens())
+        .with_p2sh_hashes(input.p2sh_hashes())
+        .with_history();
+        Ok(Interpreter { interpreter })
+    }
+
+    /// Interpreter for `inputScript` followed by `lockScript`, run against a synthetic tx.
+    /// Without `txContext`, signature checks, lock times and introspection fail.
+    #[wasm_bindgen(js_name = fromScripts)]
+    pub fn from_scripts(
+        ecc: &ECC,
+        input_script: &Script,
+        lock_script: &Script,
+        tx_context: Option<TxContext>,
+        flags: u32,
+    ) -> Result<Interpreter, ScriptError> {
+        let interpreter = ScriptInterpreter::from_scripts(
+            input_script.script(),
+            lock_script.script(),
+            tx_context.map(|tx_context| tx_context.0),
+            Arc::clone(&ecc.0),
+            VerifyFlags::from_bits_truncate(flags),
+        ).map_err(|err| ScriptError(err.into()))?
+        .with_history();
+        Ok(Interpreter { interpreter })
     }
 
     pub fn next(&mut self) -> Option<ScriptError> {
         self.interpreter.run_next_op().map_err(ScriptError).err()
     }
 
+    /// Steps back one op. Returns false if already at the first op.
+    pub fn prev(&mut self) -> bool {
+        self.interpreter.step_back()
+    }
+
+    /// Moves to the state after `step` ops, running or reverting ops as required.
+    pub fn seek(&mut self, step: usize) -> Option<ScriptError> {
+        self.interpreter.goto(step).map_err(ScriptError).err()
+    }
+
+    /// Number of ops run so far.
+    #[wasm_bindgen(js_name = numSteps)]
+    pub fn num_steps(&self) -> usize {
+        self.interpreter.num_steps()
+    }
+
+    /// Runs ops until a breakpoint is hit, the script finishes or an op fails.
+    #[wasm_bindgen(js_name = runUntilBreak)]
+    pub fn run_until_break(&mut self) -> StopReason {
+        StopReason(self.interpreter.run_until_break())
+    }
+
+    /// Runs all remaining ops, ignoring breakpoints.
+    #[wasm_bindgen(js_name = runToEnd)]
+    pub fn run_to_end(&mut self) -> StopReason {
+        StopReason(self.interpreter.run_to_end())
+    }
+
+    /// Breaks before the op at `instructionPointer` of `phase`, which defaults to "LockScript".
+    #[wasm_bindgen(js_name = addOpBreakpoint)]
+    pub fn add_op_breakpoint(
+        &mut self,
+        instruction_pointer: usize,
+        phase: Option<String>,
+    ) -> Result<usize, JsValue> {
+        let phase = match phase.as_deref() {
+            None | Some("LockScript") => ScriptPhase::LockScript,
+            Some("InputScript") => ScriptPhase::InputScript,
+            Some("P2shHashCheck") => ScriptPhase::P2shHashCheck,
+            Some(phase) => return Err(format!("Unknown phase: {}", phase).into()),
+        };
+        Ok(self.interpreter.add_breakpoint(Breakpoint::Op { phase, instruction_pointer }))
+    }
+
+    /// Breaks before ops compiled from line `srcLine` of `srcFile`.
+    #[wasm_bindgen(js_name = addSourceBreakpoint)]
+    pub fn add_source_breakpoint(&mut self, src_file: String, src_line: u32) -> usize {
+        self.interpreter.add_breakpoint(Breakpoint::Source { src_file, src_line })
+    }
+
+    /// Breaks before every op with the given name, e.g. "OP_CHECKSIG".
+    #[wasm_bindgen(js_name = addOpcodeBreakpoint)]
+    pub fn add_opcode_breakpoint(&mut self, opcode: String) -> usize {
+        self.interpreter.add_breakpoint(Breakpoint::Opcode(opcode))
+    }
+
+    /// Breaks once the stack has more than `depth` items.
+    #[wasm_bindgen(js_name = addStackDepthBreakpoint)]
+    pub fn add_stack_depth_breakpoint(&mut self, depth: usize) -> usize {
+        self.interpreter.add_breakpoint(Breakpoint::StackDepthAbove(depth))
+    }
+
+    /// Breaks once an item named `name` has been pushed.
+    #[wasm_bindgen(js_name = addItemPushedBreakpoint)]
+    pub fn add_item_pushed_breakpoint(&mut self, name: String) -> usize {
+        self.interpreter.add_breakpoint(Breakpoint::ItemPushed(name))
+    }
+
+    #[wasm_bindgen(js_name = removeBreakpoint)]
+    pub fn remove_breakpoint(&mut self, id: usize) -> bool {
+        self.interpreter.remove_breakpoint(id)
+    }
+
+    #[wasm_bindgen(js_name = clearBreakpoints)]
+    pub fn clear_breakpoints(&mut self) {
+        self.interpreter.clear_breakpoints()
+    }
+
+    /// Runs all remaining ops and checks whether the input is valid.
+    pub fn run(&mut self) -> VerificationResult {
+        VerificationResult(self.interpreter.run())
+    }
+
+    /// Runs all remaining ops like `run`, and returns the recorded trace as JSON.
+    pub fn trace(&mut self) -> Result<String, JsValue> {
+        self.interpreter.trace().to_json().map_err(|err| err.to_string().into())
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.interpreter.flags().bits()
+    }
+
+    #[wasm_bindgen(js_name = opCount)]
+    pub fn op_count(&self) -> usize {
+        self.interpreter.op_count()
+    }
+
+    #[wasm_bindgen(js_name = sigChecks)]
+    pub fn sig_checks(&self) -> usize {
+        self.interpreter.sig_checks()
+    }
+
+    #[wasm_bindgen(js_name = maxStackDepth)]
+    pub fn max_stack_depth(&self) -> usize {
+        self.interpreter.resource_usage().max_stack_depth
+    }
+
+    #[wasm_bindgen(js_name = maxElementSize)]
+    pub fn max_element_size(&self) -> usize {
+        self.interpreter.resource_usage().max_element_size
+    }
+
+    #[wasm_bindgen(js_name = maxScriptSize)]
+    pub fn max_script_size(&self) -> usize {
+        self.interpreter.resource_usage().max_script_size
+    }
+
     #[wasm_bindgen(js_name = isFinished)]
     pub fn is_finished(&self) -> bool {
         self.interpreter.is_finished()
@@ -85,11 +253,59 @@ impl Interpreter {
         self.interpreter.instruction_pointer()
     }
 
+    /// The current phase of evaluation, either "InputScript", "P2shHashCheck" or "LockScript".
+    pub fn phase(&self) -> String {
+        format!("{:?}", self.interpreter.phase())
+    }
+
+    /// The script of the current phase, which the instruction pointer refers to.
+    pub fn script(&self) -> Script {
+        Script::new(Arc::clone(self.interpreter.script().ops_arc()))
+    }
+
     #[wasm_bindgen(js_name = nextOp)]
-    pub fn next_op(&self) -> Op {
-        self.input.lock_script()
-            .expect("Input must have lock_script")
-            .op_at(self.interpreter.instruction_pointer())
+    pub fn next_op(&self) -> Option<Op> {
+        let instruction_pointer = self.interpreter.instruction_pointer();
+        if instruction_pointer >= self.interpreter.script().ops_arc().len() {
+            return None;
+        }
+        Some(self.script().op_at(instruction_pointer))
+    }
+
+    /// The scriptCode signatures currently commit to, after the last executed OP_CODESEPARATOR.
+    #[wasm_bindgen(js_name = scriptCode)]
+    pub fn script_code(&self) -> Result<ByteArray, JsValue> {
+        let script_code = self.interpreter.script_code().map_err(|err| err.to_string())?;
+        Ok(ByteArray::from_byte_array(script_code))
+    }
+
+    #[wasm_bindgen(js_name = numSigScriptCodes)]
+    pub fn num_sig_script_codes(&self) -> usize {
+        self.interpreter.sig_script_codes().len()
+    }
+
+    /// The scriptCode used by the `idx`-th signature check executed so far.
+    #[wasm_bindgen(js_name = sigScriptCodeAt)]
+    pub fn sig_script_code_at(&self, idx: usize) -> Option<SigScriptCode> {
+        self.interpreter.sig_script_codes().get(idx).cloned().map(SigScriptCode)
+    }
+}
+
+#[wasm_bindgen]
+impl SigScriptCode {
+    #[wasm_bindgen(js_name = instructionPointer)]
+    pub fn instruction_pointer(&self) -> usize {
+        self.0.instruction_pointer
+    }
+
+    #[wasm_bindgen(js_name = codeSeparator)]
+    pub fn code_separator(&self) -> usize {
+        self.0.code_separator
+    }
+
+    #[wasm_bindgen(js_name = scriptCode)]
+    pub fn script_code(&self) -> ByteArray {
+        ByteArray::from_byte_array(self.0.script_code.clone())
     }
 }
 
@@ -144,6 +360,73 @@ impl StackItem {
     pub fn delta(&self) -> String {
         format!("{:?}", self.item().delta)
     }
+
+    /// "ECDSA" or "Schnorr" if the item is the result of a signature check.
+    #[wasm_bindgen(js_name = sigScheme)]
+    pub fn sig_scheme(&self) -> Option<String> {
+        self.item().sig_scheme.map(|scheme| match scheme {
+            SignatureScheme::Ecdsa => "ECDSA".to_string(),
+            SignatureScheme::Schnorr => "Schnorr".to_string(),
+        })
+    }
+}
+
+#[wasm_bindgen]
+impl StopReason {
+    /// Either "breakpoint", "finished" or "error".
+    pub fn kind(&self) -> String {
+        use iguana_interpreter::StopReason::*;
+        match self.0 {
+            Breakpoint(_) => "breakpoint",
+            Finished => "finished",
+            Error(_) => "error",
+        }.to_string()
+    }
+
+    /// Id of the breakpoint which has been hit.
+    #[wasm_bindgen(js_name = breakpointId)]
+    pub fn breakpoint_id(&self) -> Option<usize> {
+        match self.0 {
+            iguana_interpreter::StopReason::Breakpoint(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// The error of the failed op.
+    pub fn error(&self) -> Option<ScriptError> {
+        match &self.0 {
+            iguana_interpreter::StopReason::Error(err) => Some(ScriptError(err.clone())),
+            _ => None,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl VerificationResult {
+    pub fn success(&self) -> bool {
+        self.0.success
+    }
+
+    pub fn stack(&self) -> Stack {
+        Stack {
+            items: self.0.stack.clone().into(),
+        }
+    }
+
+    /// Description of why the verification failed.
+    pub fn failure(&self) -> Option<String> {
+        self.0.failure.as_ref().map(|failure| failure.to_string())
+    }
+
+    /// The script error which made the verification fail, if any.
+    pub fn error(&self) -> Option<ScriptError> {
+        match &self.0.failure {
+            Some(iguana_interpreter::VerificationFailure::ScriptError(err)) => {
+                Some(ScriptError(err.clone()))
+            }
+            _ => None,
+        }
+    }
 }
 
 #[wasm_bindgen]
@@ -154,15 +437,69 @@ impl ScriptError {
             InvalidPubKey(..) => "Invalid public key".to_string(),
             InvalidSignatureFormat(..) => "Invalid signature format".to_string(),
             InvalidSignature(..) => "Invalid signature".to_string(),
+            NonDerSignature(..) => "Signature not strictly DER encoded".to_string(),
+            HighSSignature(..) => "Signature has a high S value".to_string(),
+            UndefinedSigHashType(sig_hash_type) => format!("Undefined sighash type: {:02x}", sig_hash_type),
+            IllegalForkId(..) => "SIGHASH_FORKID not enabled".to_string(),
+            MustUseForkId(..) => "Signature must use SIGHASH_FORKID".to_string(),
             EqualVerifyFailed(..) => "OP_EQUALVERIFY failed".to_string(),
             VerifyFailed => "OP_VERIFY failed".to_string(),
+            CheckMultiSigVerifyFailed => "OP_CHECKMULTISIGVERIFY failed".to_string(),
+            InvalidPubKeyCount(count) => format!("Invalid public key count: {}", count),
+            InvalidSignatureCount(count) => format!("Invalid signature count: {}", count),
+            InvalidBitfieldSize { .. } => "Invalid multisig bitfield size".to_string(),
+            InvalidBitRange(..) => "Invalid multisig bitfield range".to_string(),
+            InvalidBitCount { .. } => "Invalid multisig bitfield bit count".to_string(),
+            SchnorrSignatureInLegacyMultisig(..) => "Schnorr signature in legacy multisig".to_string(),
+            InvalidSchnorrSignatureLength(..) => "Invalid Schnorr signature length".to_string(),
+            MultiSigNullFail => "Multisig failed with non-null signatures".to_string(),
+            P2shHashMismatch => "P2SH redeem script hash mismatch".to_string(),
+            InvalidRedeemScript(msg) => format!("Invalid redeem script: {}", msg),
+            NonNullDummy(..) => "OP_CHECKMULTISIG dummy not empty".to_string(),
+            NonMinimalPush(..) => "Push not minimally encoded".to_string(),
+            SigPushOnly(..) => "Input script not push only".to_string(),
+            DiscourageUpgradableNops(opcode) => format!("{:?}: Upgradable NOPs are discouraged", opcode),
+            StackSize(..) => "Stack size limit exceeded".to_string(),
+            PushSize(..) => "Stack item size limit exceeded".to_string(),
+            ScriptSize(..) => "Script size limit exceeded".to_string(),
+            OpCount(..) => "Op count limit exceeded".to_string(),
+            InputSigChecks { .. } => "Input SigChecks limit exceeded".to_string(),
             NotImplemented => "Opcode not implemented".to_string(),
             ScriptFinished => "Script finished".to_string(),
             InvalidDataType => "Invalid data type for operation".to_string(),
-            StackEmpty => "Stack empty".to_string(),
+            StackEmpty(opcode) => format!("{:?}: Stack empty", opcode),
+            AltStackEmpty(opcode) => format!("{:?}: Alt stack empty", opcode),
             OpcodeMsg(opcode, msg) => format!("{:?}: {}", opcode, msg),
             UnbalancedConditionals(..) => "Unbalanced conditionals".to_string(),
+            UnterminatedConditionals(..) => "Unterminated OP_IF/OP_NOTIF".to_string(),
+            MinimalIf(opcode, _) => format!("{:?}: Condition not minimally encoded", opcode),
             InvalidOpcode(code) => format!("Invalid opcode: {:02x}", code),
+            IntrospectionStackEmpty(op_name) => format!("{}: Stack empty", op_name),
+            InvalidInputIndex { .. } => "Invalid input index".to_string(),
+            InvalidOutputIndex { .. } => "Invalid output index".to_string(),
+            MissingUtxo(..) => "Missing UTXO".to_string(),
+            NoTxContext => "No transaction context".to_string(),
+            NoHistory => "No history recorded".to_string(),
+            Construction(err) => match err {
+                ConstructionError::InputIndexOutOfRange { .. } => "Input index out of range".to_string(),
+                ConstructionError::MissingLockScript(..) => "Missing lock script".to_string(),
+                ConstructionError::MissingIsP2sh(..) => "Missing P2SH flag of input".to_string(),
+                ConstructionError::InvalidLockScript(..) => "Invalid lock script".to_string(),
+            },
+            InvalidTokenPrefix(..) => "Invalid token prefix".to_string(),
+            IntegerOverflow(opcode) => format!("{:?}: Integer overflow", opcode),
+            DivisionByZero(opcode) => format!("{:?}: Division by zero", opcode),
+            DisabledOpcode(opcode) => format!("{:?}: Disabled opcode", opcode),
+            ScriptNumOverflow(..) => "Script number overflow".to_string(),
+            NonMinimalScriptNum(..) => "Script number not minimally encoded".to_string(),
+            NegativeLockTime(opcode, _) => format!("{:?}: Negative lock time", opcode),
+            LockTimeTypeMismatch { .. } => "Lock time type mismatch".to_string(),
+            LockTimeUnsatisfied { .. } => "Lock time not satisfied".to_string(),
+            LockTimeInputFinal => "Input sequence is final".to_string(),
+            SequenceTxVersion(..) => "Transaction version too low for OP_CHECKSEQUENCEVERIFY".to_string(),
+            SequenceDisabled(..) => "Input sequence has disable flag set".to_string(),
+            SequenceTypeMismatch { .. } => "Relative lock time type mismatch".to_string(),
+            SequenceUnsatisfied { .. } => "Relative lock time not satisfied".to_string(),
             InvalidDepth(depth) => format!("Invalid depth: {}", depth),
             InvalidInteger(integer) => format!("Invalid integer: {}", integer),
             InvalidConversion(conversion) => format!("Invalid conversion: {}", conversion),
@@ -175,6 +512,14 @@ impl ScriptError {
         match &self.0 {
             InvalidPubKey(array) | 
             InvalidSignatureFormat(array) | 
+            NonDerSignature(array) | 
+            HighSSignature(array) | 
+            NonNullDummy(array) | 
+            NonMinimalPush(array) | 
+            MinimalIf(_, array) | 
+            SchnorrSignatureInLegacyMultisig(array) | 
+            InvalidSchnorrSignatureLength(array) | 
+            NonMinimalScriptNum(array) | 
             InvalidSignature(array, _) | 
             EqualVerifyFailed(array, _) => Some(ByteArray::from_byte_array(array.clone())),
             _ => None,
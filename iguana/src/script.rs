@@ -1,7 +1,8 @@
-use bitcoin_cash::{TaggedOp, Opcode, serialize_op};
+use bitcoin_cash::{TaggedOp, Opcode, deserialize_ops, serialize_op, serialize_ops};
 use wasm_bindgen::prelude::*;
 use crate::ByteArray;
 use std::sync::Arc;
+use iguana_interpreter::{introspection_opcode_name, script_from_asm, script_to_asm};
 
 #[wasm_bindgen]
 pub struct Script {
@@ -23,6 +24,14 @@ impl Script {
         Script { ops }
     }
 
+    fn from_ops(ops: Vec<bitcoin_cash::Op>) -> Self {
+        Script::new(ops.into_iter().map(TaggedOp::from_op).collect::<Vec<_>>().into())
+    }
+
+    pub fn script(&self) -> bitcoin_cash::Script {
+        bitcoin_cash::Script::new(Arc::clone(&self.ops))
+    }
+
     pub fn op_at(&self, idx: usize) -> Op {
         Op {
             ops: Arc::clone(&self.ops),
@@ -33,6 +42,30 @@ impl Script {
 
 #[wasm_bindgen]
 impl Script {
+    /// Parses a script in assembly, e.g. "OP_DUP OP_HASH160 <0x..> OP_EQUALVERIFY OP_CHECKSIG".
+    #[wasm_bindgen(js_name = fromAsm)]
+    pub fn from_asm(asm: &str) -> Result<Script, JsValue> {
+        Ok(Script::from_ops(script_from_asm(asm).map_err(fmt_err)?))
+    }
+
+    #[wasm_bindgen(js_name = fromHex)]
+    pub fn from_hex(script_hex: &str) -> Result<Script, JsValue> {
+        let script_ser = hex::decode(script_hex).map_err(fmt_err)?;
+        Ok(Script::from_ops(deserialize_ops(&script_ser).map_err(fmt_err)?))
+    }
+
+    #[wasm_bindgen(js_name = toHex)]
+    pub fn to_hex(&self) -> Result<String, JsValue> {
+        let script_ser = serialize_ops(self.ops.iter().map(|op| &op.op)).map_err(fmt_err)?;
+        Ok(hex::encode(script_ser.data()))
+    }
+
+    /// The script in assembly, e.g. "OP_DUP OP_HASH160 <0x..> OP_EQUALVERIFY OP_CHECKSIG".
+    #[wasm_bindgen(js_name = toAsm)]
+    pub fn to_asm(&self) -> String {
+        script_to_asm(&self.ops)
+    }
+
     pub fn ops(&self) -> Vec<JsValue> {
         self.ops.iter()
             .enumerate()
@@ -56,7 +89,10 @@ impl Op {
         use bitcoin_cash::Op::*;
         match self.tagged_op().op {
             Code(code) => format!("{:?}", code).into(),
-            Invalid(code) => format!("INVALID_{}", code).into(),
+            Invalid(code) => match introspection_opcode_name(code) {
+                Some(name) => name.into(),
+                None => format!("INVALID_{}", code).into(),
+            },
             PushByteArray { ref array, .. } => ByteArray::from_byte_array(array.clone()).into(),
             PushBoolean(boolean) => boolean.into(),
             PushInteger(int) => int.value().into(),
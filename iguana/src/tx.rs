@@ -1,17 +1,26 @@
 use wasm_bindgen::prelude::*;
-use bitcoin_cash::{json_to_tx, Hashed};
+use bitcoin_cash::{json_to_tx, tx_to_json, deserialize_ops, serialize_ops, BitcoinCode, Hashed, Op, UnhashedTx};
+use serde::Deserialize;
+use iguana_interpreter::{
+    script_token, split_token_prefix, NftCapability, P2shHashes, TokenData, TxTokens, TxVerifier,
+    VerifyFlags,
+};
 use std::sync::Arc;
-use crate::{Script, ByteArray};
+use crate::{Script, ByteArray, ECC, ScriptError};
 
 #[wasm_bindgen]
 pub struct Tx {
     tx: Arc<bitcoin_cash::Tx>,
+    tokens: Arc<TxTokens>,
+    p2sh_hashes: Arc<P2shHashes>,
 }
 
 #[wasm_bindgen]
 #[derive(Clone)]
 pub struct TxInput {
     tx: Arc<bitcoin_cash::Tx>,
+    tokens: Arc<TxTokens>,
+    p2sh_hashes: Arc<P2shHashes>,
     input_idx: usize,
 }
 
@@ -27,10 +36,85 @@ pub struct TxOutput {
     output_idx: usize,
 }
 
+#[wasm_bindgen]
+pub struct Token(TokenData);
+
+#[wasm_bindgen]
+pub struct TxReport(iguana_interpreter::TxReport);
+
+#[wasm_bindgen]
+pub struct InputReport(iguana_interpreter::InputReport);
+
+/// Output spent by an input, as passed to `Tx.fromHex`.
+#[derive(Deserialize)]
+struct PrevOutput {
+    value: u64,
+    /// Hex encoded lock script.
+    script: String,
+}
+
 fn fmt_err(err: impl std::fmt::Display) -> JsValue {
     err.to_string().into()
 }
 
+/// Checks whether the script is a P2SH output script, `OP_HASH160 <20 bytes> OP_EQUAL`.
+fn is_p2sh_script(script: &[u8]) -> bool {
+    script.len() == 23 && script[0] == 0xa9 && script[1] == 0x14 && script[22] == 0x87
+}
+
+/// Sets the lock script, value and whether it is P2SH for each input from its spent output,
+/// and returns the token data and P2SH script hashes of the spent outputs. The lock script
+/// of P2SH inputs is the redeem script, which is pushed last by the input script.
+fn attach_prev_outputs(
+    tx: &mut UnhashedTx,
+    prev_outputs: Vec<PrevOutput>,
+) -> Result<(Vec<Option<TokenData>>, P2shHashes), JsValue> {
+    if prev_outputs.len() != tx.inputs.len() {
+        return Err(format!(
+            "Tx has {} inputs, but {} previous outputs were given",
+            tx.inputs.len(),
+            prev_outputs.len(),
+        ).into());
+    }
+    let mut tokens = Vec::with_capacity(tx.inputs.len());
+    let mut p2sh_hashes = P2shHashes::default();
+    for (input_idx, (input, prev_output)) in tx.inputs.iter_mut().zip(prev_outputs).enumerate() {
+        let script = hex::decode(&prev_output.script).map_err(fmt_err)?;
+        // outputs with an invalid prefix are kept as is, their lock script then fails on 0xef
+        let (token, bytecode) = split_token_prefix(&script).unwrap_or((None, &script));
+        let is_p2sh = is_p2sh_script(bytecode);
+        // the redeem script is checked against this hash when running the input
+        let script_hash = if is_p2sh {
+            Some(bitcoin_cash::ByteArray::from(bytecode[2..22].to_vec()))
+        } else {
+            None
+        };
+        let lock_script_ser = if is_p2sh {
+            match input.script.ops_arc().last().map(|op| &op.op) {
+                Some(Op::PushByteArray { array, .. }) => array.to_vec(),
+                _ => {
+                    return Err(format!("P2SH input {} doesn't push a redeem script", input_idx).into())
+                }
+            }
+        } else {
+            bytecode.to_vec()
+        };
+        input.lock_script = Some(bitcoin_cash::Script::from_ops(deserialize_ops(&lock_script_ser).map_err(fmt_err)?));
+        input.value = Some(prev_output.value);
+        input.is_p2sh = Some(is_p2sh);
+        tokens.push(token);
+        p2sh_hashes.inputs.push(script_hash);
+    }
+    Ok((tokens, p2sh_hashes))
+}
+
+impl Tx {
+    fn new(tx: bitcoin_cash::Tx) -> Self {
+        let tokens = Arc::new(TxTokens::from_tx(&tx));
+        Tx { tx: Arc::new(tx), tokens, p2sh_hashes: Arc::new(P2shHashes::default()) }
+    }
+}
+
 impl TxInput {
     pub fn tx(&self) -> Arc<bitcoin_cash::Tx> {
         Arc::clone(&self.tx)
@@ -39,14 +123,44 @@ impl TxInput {
     pub fn input_idx(&self) -> usize {
         self.input_idx
     }
+
+    /// Token data of the tx, passed on to the interpreter.
+    pub fn tokens(&self) -> TxTokens {
+        TxTokens::clone(&self.tokens)
+    }
+
+    /// Script hashes of the spent P2SH outputs, passed on to the interpreter.
+    pub fn p2sh_hashes(&self) -> P2shHashes {
+        P2shHashes::clone(&self.p2sh_hashes)
+    }
 }
 
 #[wasm_bindgen]
 impl Tx {
     #[wasm_bindgen(js_name = fromJson)]
     pub fn from_json(json: &str) -> Result<Tx, JsValue> {
+        Ok(Tx::new(json_to_tx(json).map_err(fmt_err)?.hashed()))
+    }
+
+    /// Deserializes a raw tx and attaches the outputs it spends, given as JSON array
+    /// `[{"value": <satoshis>, "script": "<lock script hex>"}, ...]` in input order.
+    /// Lock scripts may start with a CashTokens prefix.
+    #[wasm_bindgen(js_name = fromHex)]
+    pub fn from_hex(tx_hex: &str, prev_outputs: &str) -> Result<Tx, JsValue> {
+        let tx_ser = hex::decode(tx_hex).map_err(fmt_err)?;
+        let (mut tx, rest) = UnhashedTx::deser(tx_ser.into()).map_err(fmt_err)?;
+        if !rest.is_empty() {
+            return Err(format!("Tx has {} trailing bytes", rest.len()).into());
+        }
+        let prev_outputs = serde_json::from_str(prev_outputs).map_err(fmt_err)?;
+        let (input_tokens, p2sh_hashes) = attach_prev_outputs(&mut tx, prev_outputs)?;
+        let tx = tx.hashed();
+        let mut tokens = TxTokens::from_tx(&tx);
+        tokens.inputs = input_tokens;
         Ok(Tx {
-            tx: Arc::new(json_to_tx(json).map_err(fmt_err)?.hashed()),
+            tx: Arc::new(tx),
+            tokens: Arc::new(tokens),
+            p2sh_hashes: Arc::new(p2sh_hashes),
         })
     }
 
@@ -54,6 +168,23 @@ impl Tx {
         self.tx.hash().to_vec_le()
     }
 
+    /// The raw tx, as broadcast to the network.
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.tx.ser().to_vec()
+    }
+
+    #[wasm_bindgen(js_name = toHex)]
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.tx.ser().data())
+    }
+
+    /// The tx as JSON, in the format read by `Tx.fromJson`.
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsValue> {
+        tx_to_json(self.tx.unhashed_tx()).map_err(fmt_err)
+    }
+
     #[wasm_bindgen(js_name = hashHex)]
     pub fn hash_hex(&self) -> String {
         self.tx.hash().to_hex_le()
@@ -77,6 +208,8 @@ impl Tx {
     pub fn input_at(&self, input_idx: usize) -> TxInput {
         TxInput {
             tx: Arc::clone(&self.tx),
+            tokens: Arc::clone(&self.tokens),
+            p2sh_hashes: Arc::clone(&self.p2sh_hashes),
             input_idx,
         }
     }
@@ -85,11 +218,23 @@ impl Tx {
         (0..self.tx.inputs().len()).into_iter().map(|idx| {
             TxInput {
                 tx: Arc::clone(&self.tx),
+                tokens: Arc::clone(&self.tokens),
+                p2sh_hashes: Arc::clone(&self.p2sh_hashes),
                 input_idx: idx,
             }.into()
         }).collect()
     }
 
+    /// Verifies all inputs with `flags`, e.g. `standardVerifyFlags()`.
+    #[wasm_bindgen(js_name = verifyAll)]
+    pub fn verify_all(&self, ecc: &ECC, flags: u32) -> TxReport {
+        let flags = VerifyFlags::from_bits_truncate(flags);
+        let verifier = TxVerifier::new(Arc::clone(&self.tx), Arc::clone(&ecc.0), flags)
+            .with_tokens(TxTokens::clone(&self.tokens))
+            .with_p2sh_hashes(P2shHashes::clone(&self.p2sh_hashes));
+        TxReport(verifier.verify_all())
+    }
+
     #[wasm_bindgen(js_name = numOutputs)]
     pub fn num_outputs(&self) -> usize {
         self.tx.outputs().len()
@@ -140,6 +285,37 @@ impl TxInput {
     pub fn value(&self) -> Option<u64> {
         self.input().value
     }
+
+    /// The input as JSON, with scripts and the outpoint's tx hash hex encoded.
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsValue> {
+        let input = self.input();
+        let script_hex = |script: &bitcoin_cash::Script| -> Result<String, JsValue> {
+            let script_ser = serialize_ops(script.ops_arc().iter().map(|op| &op.op)).map_err(fmt_err)?;
+            Ok(hex::encode(script_ser.data()))
+        };
+        let lock_script = match &input.lock_script {
+            Some(lock_script) => Some(script_hex(lock_script)?),
+            None => None,
+        };
+        let json = serde_json::json!({
+            "prevOut": {
+                "txHash": input.prev_out.tx_hash.to_hex_le(),
+                "vout": input.prev_out.vout,
+            },
+            "script": script_hex(&input.script)?,
+            "sequence": input.sequence,
+            "lockScript": lock_script,
+            "value": input.value,
+            "isP2sh": input.is_p2sh,
+        });
+        Ok(json.to_string())
+    }
+
+    /// Tokens held by the spent UTXO, if its locking bytecode has a token prefix.
+    pub fn token(&self) -> Option<Token> {
+        self.tokens.inputs.get(self.input_idx).cloned().flatten().map(Token)
+    }
 }
 
 impl TxOutput {
@@ -157,6 +333,51 @@ impl TxOutput {
     pub fn value(&self) -> u64 {
         self.output().value
     }
+
+    /// Tokens held by the output, if its script has a token prefix.
+    pub fn token(&self) -> Option<Token> {
+        script_token(&self.output().script).map(Token)
+    }
+}
+
+#[wasm_bindgen]
+impl Token {
+    pub fn category(&self) -> ByteArray {
+        ByteArray::from_byte_array(self.0.category.clone())
+    }
+
+    /// Category ID in the byte order used for display, like tx hashes.
+    #[wasm_bindgen(js_name = categoryHex)]
+    pub fn category_hex(&self) -> String {
+        let mut category = self.0.category.to_vec();
+        category.reverse();
+        hex::encode(category)
+    }
+
+    /// Fungible token amount, 0 if there are no fungible tokens.
+    pub fn amount(&self) -> u64 {
+        self.0.amount
+    }
+
+    #[wasm_bindgen(js_name = hasNft)]
+    pub fn has_nft(&self) -> bool {
+        self.0.nft.is_some()
+    }
+
+    /// "none", "mutable" or "minting" if there is an NFT.
+    #[wasm_bindgen(js_name = nftCapability)]
+    pub fn nft_capability(&self) -> Option<String> {
+        self.0.nft.as_ref().map(|nft| match nft.capability {
+            NftCapability::None => "none".to_string(),
+            NftCapability::Mutable => "mutable".to_string(),
+            NftCapability::Minting => "minting".to_string(),
+        })
+    }
+
+    #[wasm_bindgen(js_name = nftCommitment)]
+    pub fn nft_commitment(&self) -> Option<ByteArray> {
+        self.0.nft.as_ref().map(|nft| ByteArray::from_byte_array(nft.commitment.clone()))
+    }
 }
 
 #[wasm_bindgen]
@@ -175,3 +396,62 @@ impl TxOutpoint {
         self.vout
     }
 }
+
+#[wasm_bindgen]
+impl TxReport {
+    /// Whether all inputs are valid.
+    pub fn success(&self) -> bool {
+        self.0.success()
+    }
+
+    #[wasm_bindgen(js_name = numInputs)]
+    pub fn num_inputs(&self) -> usize {
+        self.0.inputs.len()
+    }
+
+    #[wasm_bindgen(js_name = inputAt)]
+    pub fn input_at(&self, input_idx: usize) -> Option<InputReport> {
+        self.0.inputs.get(input_idx).cloned().map(InputReport)
+    }
+
+    pub fn inputs(&self) -> Vec<JsValue> {
+        self.0.inputs.iter().cloned().map(|input| InputReport(input).into()).collect()
+    }
+}
+
+#[wasm_bindgen]
+impl InputReport {
+    #[wasm_bindgen(js_name = inputIdx)]
+    pub fn input_idx(&self) -> usize {
+        self.0.input_idx
+    }
+
+    pub fn success(&self) -> bool {
+        self.0.success
+    }
+
+    /// Description of why the input failed.
+    pub fn failure(&self) -> Option<String> {
+        self.0.failure.as_ref().map(|failure| failure.to_string())
+    }
+
+    /// The script error which made the input fail, if any.
+    pub fn error(&self) -> Option<ScriptError> {
+        match &self.0.failure {
+            Some(iguana_interpreter::VerificationFailure::ScriptError(err)) => {
+                Some(ScriptError(err.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    #[wasm_bindgen(js_name = opCount)]
+    pub fn op_count(&self) -> usize {
+        self.0.usage.op_count
+    }
+
+    #[wasm_bindgen(js_name = sigChecks)]
+    pub fn sig_checks(&self) -> usize {
+        self.0.usage.sig_checks
+    }
+}